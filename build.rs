@@ -0,0 +1,44 @@
+//! Hashes everything that the generated site depends on besides the content,
+//! so that the build cache is discarded when it changes (see
+//! `TEMPLATE_VERSION` in `src/cache.rs`).
+
+use std::{fs, path::Path};
+
+/// Files and directories with the templates, scripts, styles and assets of the
+/// site, and the dependencies that render it.
+const SOURCES: &[&str] = &["src", "assets", "public", "Cargo.toml", "Cargo.lock"];
+
+fn main() {
+    let mut hasher = blake3::Hasher::new();
+    for source in SOURCES {
+        let path = Path::new(source);
+        // The lockfile isn't committed, so it might not be there.
+        if path.exists() {
+            println!("cargo::rerun-if-changed={source}");
+            hash(path, &mut hasher);
+        }
+    }
+
+    println!(
+        "cargo::rustc-env=TEMPLATE_VERSION={}",
+        hasher.finalize().to_hex()
+    );
+}
+
+/// Adds the paths and contents of the file or directory at `path`, in a fixed
+/// order.
+fn hash(path: &Path, hasher: &mut blake3::Hasher) {
+    if path.is_dir() {
+        let mut children = fs::read_dir(path)
+            .expect("Sources should be readable")
+            .map(|entry| entry.expect("Sources should be readable").path())
+            .collect::<Vec<_>>();
+        children.sort();
+        for child in children {
+            hash(&child, hasher);
+        }
+    } else {
+        hasher.update(path.to_string_lossy().as_bytes());
+        hasher.update(&fs::read(path).expect("Sources should be readable"));
+    }
+}
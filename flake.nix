@@ -39,16 +39,13 @@
               pkgs.openssl
               pkgs.pkg-config
 
-              pkgs.watchexec
-              pkgs.live-server
-
               pkgs.tailwindcss_4
               pkgs.static-web-server
               pkgs.wrangler
               pkgs.imagemagick
 
               (pkgs.writeShellScriptBin "deploy" ''
-                cargo run --release && wrangler pages deploy target/release/site/ --project-name "odilf-site" --branch main
+                cargo run --release -- build --release --strict && wrangler pages deploy target/release/site/ --project-name "odilf-site" --branch main
               '')
             ];
           };
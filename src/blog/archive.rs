@@ -0,0 +1,131 @@
+//! Archive of the blog by date.
+//!
+//! There is a page for each year (`/blog/2024/`) and month (`/blog/2024/05/`)
+//! with posts, and an overview of all the years at `/blog/archive/`. Posts
+//! without a date are left out.
+
+use jiff::civil::Date;
+use maud::{Markup, html};
+use std::{cmp::Reverse, collections::BTreeMap};
+
+use crate::{
+    PageMeta,
+    blog::{BlogEntry, posts},
+    collection::DerivedOutput,
+    components::{back, back_to},
+};
+
+/// Posts by year and month, newest first.
+type Archive<'a> = BTreeMap<Reverse<i16>, BTreeMap<Reverse<i8>, Vec<&'a BlogEntry>>>;
+
+/// The archive pages of `entries`, which should be sorted.
+pub fn outputs(entries: &[BlogEntry]) -> Vec<DerivedOutput> {
+    let mut archive = Archive::new();
+    for entry in entries {
+        if let Some(date) = entry.metadata.date {
+            archive
+                .entry(Reverse(date.year()))
+                .or_default()
+                .entry(Reverse(date.month()))
+                .or_default()
+                .push(entry);
+        }
+    }
+
+    let mut outputs = vec![DerivedOutput::Page {
+        path: "blog/archive/index.html".to_string(),
+        meta: PageMeta::titled("archive", "every post of the blog, by year."),
+        content: overview(&archive),
+    }];
+
+    for (&Reverse(year), months) in &archive {
+        outputs.push(DerivedOutput::Page {
+            path: format!("blog/{year}/index.html"),
+            meta: PageMeta {
+                date: newest(months.values().flatten()),
+                ..PageMeta::titled(year.to_string(), format!("posts of {year}."))
+            },
+            content: year_page(year, months),
+        });
+
+        for (&Reverse(month), entries) in months {
+            let name = month_name(year, month);
+            outputs.push(DerivedOutput::Page {
+                path: format!("blog/{year}/{month:02}/index.html"),
+                meta: PageMeta {
+                    date: newest(entries.iter()),
+                    ..PageMeta::titled(format!("{name} {year}"), format!("posts of {name} {year}."))
+                },
+                content: month_page(year, month, entries),
+            });
+        }
+    }
+
+    outputs
+}
+
+fn newest<'a>(entries: impl Iterator<Item = &'a &'a BlogEntry>) -> Option<Date> {
+    entries.filter_map(|entry| entry.metadata.date).max()
+}
+
+fn month_name(year: i16, month: i8) -> String {
+    Date::new(year, month, 1)
+        .expect("Month comes from a valid date")
+        .strftime("%B")
+        .to_string()
+}
+
+fn overview(archive: &Archive) -> Markup {
+    html! {
+        (back())
+
+        h1 { "archive" }
+        p."pb-4 faint" { "every post of the blog, by year." }
+
+        ul."flex flex-col gap-2" {
+            @for (Reverse(year), months) in archive {
+                li."text-lg" {
+                    a."text-secondary" href=(format!("/blog/{year}/")) { (year) }
+                    span."faint" { " (" (posts(months.values().map(Vec::len).sum())) ")" }
+                }
+            }
+        }
+    }
+}
+
+fn year_page(year: i16, months: &BTreeMap<Reverse<i8>, Vec<&BlogEntry>>) -> Markup {
+    html! {
+        (back_to("/blog/archive/"))
+
+        h1 { (year) }
+        p."pb-4 faint" { (posts(months.values().map(Vec::len).sum())) " in " (year) "." }
+
+        @for (Reverse(month), entries) in months {
+            h2."text-xl mb-2" {
+                a."text-secondary" href=(format!("{month:02}/")) { (month_name(year, *month)) }
+            }
+            ul {
+                @for entry in entries {
+                    li."mb-4" { (entry.render_summary()) }
+                }
+            }
+        }
+    }
+}
+
+fn month_page(year: i16, month: i8, entries: &[&BlogEntry]) -> Markup {
+    let name = month_name(year, month);
+
+    html! {
+        (back())
+
+        h1 { (name) " " (year) }
+        p."pb-4 faint" { (posts(entries.len())) " in " (name) " " (year) "." }
+
+        ul {
+            @for entry in entries {
+                li."mb-4" { (entry.render_summary()) }
+            }
+        }
+    }
+}
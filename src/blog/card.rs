@@ -0,0 +1,220 @@
+//! Social preview cards of blog posts.
+//!
+//! Each post gets a PNG with its title, date, topics and length, which is used
+//! as the `og:image` of its page. The colors are the ones of the dark theme in
+//! `app.css`.
+//!
+//! The card is drawn with `image`, which can't render text, so the glyphs are
+//! rasterized with `ab_glyph`. The fonts (in `assets/fonts`, since they aren't
+//! served) and the logo are embedded in the binary, and changing them changes
+//! the [`crate::cache::TEMPLATE_VERSION`].
+
+use ab_glyph::{Font as _, FontRef, PxScale, ScaleFont as _, point};
+use color_eyre::eyre::{self, WrapErr as _};
+use image::{ImageFormat, Pixel as _, Rgba, RgbaImage, imageops};
+use std::{io::Cursor, sync::LazyLock};
+
+use crate::blog::BlogEntry;
+
+/// Size of the card, the one recommended for Open Graph images.
+pub const WIDTH: u32 = 1200;
+pub const HEIGHT: u32 = 630;
+
+/// File name of the card, next to the page of the post.
+pub const FILE_NAME: &str = "card.png";
+
+const PADDING: i32 = 80;
+const MAX_TITLE_LINES: usize = 3;
+const LOGO_SIZE: u32 = 120;
+
+const NEUTRAL: Rgba<u8> = Rgba([5, 5, 3, 255]);
+const PRIMARY_INTENSE: Rgba<u8> = Rgba([245, 231, 154, 255]);
+const PRIMARY_SOFT: Rgba<u8> = Rgba([208, 207, 202, 255]);
+const SECONDARY: Rgba<u8> = Rgba([246, 200, 245, 255]);
+const TERTIARY: Rgba<u8> = Rgba([196, 214, 239, 255]);
+
+static REGULAR: LazyLock<FontRef<'static>> = LazyLock::new(|| {
+    FontRef::try_from_slice(include_bytes!("../../assets/fonts/DejaVuSansMono.ttf"))
+        .expect("Embedded font is valid")
+});
+
+static BOLD: LazyLock<FontRef<'static>> = LazyLock::new(|| {
+    FontRef::try_from_slice(include_bytes!("../../assets/fonts/DejaVuSansMono-Bold.ttf"))
+        .expect("Embedded font is valid")
+});
+
+static LOGO: LazyLock<RgbaImage> = LazyLock::new(|| {
+    let logo = image::load_from_memory(include_bytes!("../../public/logo.png"))
+        .expect("Embedded logo is valid");
+    imageops::resize(&logo, LOGO_SIZE, LOGO_SIZE, imageops::FilterType::Lanczos3)
+});
+
+impl BlogEntry {
+    /// Path of the card of the post, from the root of the site.
+    pub fn card_path(&self) -> String {
+        format!("/blog/{}/{FILE_NAME}", self.slug)
+    }
+
+    /// Renders the social preview card of the post, encoded as PNG.
+    pub fn render_card(&self) -> eyre::Result<Vec<u8>> {
+        let mut card = RgbaImage::from_pixel(WIDTH, HEIGHT, NEUTRAL);
+
+        // Accent on the left, like blockquotes.
+        for (_, _, pixel) in card.enumerate_pixels_mut().filter(|(x, _, _)| *x < 16) {
+            *pixel = SECONDARY;
+        }
+
+        let title_scale = PxScale::from(68.0);
+        let max_width = WIDTH - 2 * PADDING as u32;
+        let mut y = PADDING;
+        for line in wrap(&self.metadata.title, &BOLD, title_scale, max_width) {
+            draw_text(
+                &mut card,
+                PRIMARY_INTENSE,
+                PADDING,
+                y,
+                title_scale,
+                &BOLD,
+                &line,
+            );
+            y += 84;
+        }
+
+        let info_scale = PxScale::from(34.0);
+        let mut info = format!("{} words", self.word_count);
+        if let Some(date) = self.metadata.date {
+            info = format!("{} · {info}", date.strftime("%d %b, %Y"));
+        }
+        draw_text(
+            &mut card,
+            PRIMARY_SOFT,
+            PADDING,
+            y + 24,
+            info_scale,
+            &REGULAR,
+            &info,
+        );
+
+        let topics = self
+            .metadata
+            .topics
+            .iter()
+            .map(|topic| format!("#{topic}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        draw_text(
+            &mut card,
+            TERTIARY,
+            PADDING,
+            HEIGHT as i32 - PADDING - 34,
+            info_scale,
+            &REGULAR,
+            &topics,
+        );
+
+        imageops::overlay(
+            &mut card,
+            &*LOGO,
+            i64::from(WIDTH - LOGO_SIZE) - i64::from(PADDING),
+            i64::from(HEIGHT - LOGO_SIZE) - i64::from(PADDING),
+        );
+
+        let mut png = Vec::new();
+        card.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .wrap_err("Couldn't encode social card")?;
+        Ok(png)
+    }
+}
+
+/// Splits `text` in lines that fit in `max_width`, ending with an ellipsis if
+/// it doesn't fit in [`MAX_TITLE_LINES`].
+fn wrap(text: &str, font: &FontRef, scale: PxScale, max_width: u32) -> Vec<String> {
+    let fits = |line: &str| text_width(line, font, scale) <= max_width as f32;
+
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if fits(&format!("{line} {word}")) => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+
+    if lines.len() > MAX_TITLE_LINES {
+        lines.truncate(MAX_TITLE_LINES);
+        let last = &mut lines[MAX_TITLE_LINES - 1];
+        while !last.is_empty() && !fits(&format!("{last}…")) {
+            last.pop();
+        }
+        last.push('…');
+    }
+
+    lines
+}
+
+/// Positions of the glyphs of `text` in a line starting at `(x, y)`, which is
+/// its top left corner, and the position where the line ends.
+fn layout(
+    text: &str,
+    font: &FontRef,
+    scale: PxScale,
+    x: f32,
+    y: f32,
+) -> (Vec<ab_glyph::Glyph>, f32) {
+    let font = font.as_scaled(scale);
+    let mut caret = point(x, y + font.ascent());
+    let mut previous = None;
+    let mut glyphs = Vec::new();
+    for c in text.chars() {
+        let id = font.glyph_id(c);
+        if let Some(previous) = previous {
+            caret.x += font.kern(previous, id);
+        }
+        previous = Some(id);
+
+        glyphs.push(id.with_scale_and_position(scale, caret));
+        caret.x += font.h_advance(id);
+    }
+
+    (glyphs, caret.x)
+}
+
+/// Width of `text` in a single line.
+fn text_width(text: &str, font: &FontRef, scale: PxScale) -> f32 {
+    layout(text, font, scale, 0.0, 0.0).1
+}
+
+/// Draws `text` in a single line with its top left corner at `(x, y)`.
+fn draw_text(
+    card: &mut RgbaImage,
+    color: Rgba<u8>,
+    x: i32,
+    y: i32,
+    scale: PxScale,
+    font: &FontRef,
+    text: &str,
+) {
+    let (glyphs, _) = layout(text, font, scale, x as f32, y as f32);
+    for glyph in glyphs {
+        let Some(outlined) = font.outline_glyph(glyph) else {
+            continue;
+        };
+
+        let bounds = outlined.px_bounds();
+        outlined.draw(|glyph_x, glyph_y, coverage| {
+            let x = bounds.min.x as i64 + i64::from(glyph_x);
+            let y = bounds.min.y as i64 + i64::from(glyph_y);
+            let (Ok(x), Ok(y)) = (u32::try_from(x), u32::try_from(y)) else {
+                return;
+            };
+
+            if let Some(pixel) = card.get_pixel_mut_checked(x, y) {
+                let mut color = color;
+                color.0[3] = (coverage.clamp(0.0, 1.0) * 255.0) as u8;
+                pixel.blend(&color);
+            }
+        });
+    }
+}
@@ -0,0 +1,280 @@
+//! Numbered equations, and references to them.
+//!
+//! Like in LaTeX, display math with a `\label{name}` gets the next number as
+//! its tag, and `\eqref{name}` (in math or in the text of the post) becomes a
+//! link to it. KaTeX renders each formula on its own, so labels are resolved
+//! before that, with the numbers of the whole post.
+//!
+//! KaTeX doesn't render links without trusting every command of the formula
+//! (`\href`, `\url`, `\htmlId`...), so references in math are rendered as
+//! marked text instead, which becomes a link afterwards (see
+//! [`link_references`]).
+
+use std::{collections::BTreeMap, fmt::Write as _};
+
+/// Location of a formula in the file, as a line and a column.
+pub type Position = (usize, usize);
+
+/// Marks both ends of the references in formulas. It's invisible and
+/// KaTeX keeps it with the text around it, so the reference stays together.
+const REFERENCE_MARK: char = '\u{2063}';
+
+/// The labelled equations of a post.
+#[derive(Default)]
+pub struct Equations {
+    /// Number and position of the equation of each label.
+    labels: BTreeMap<String, (u32, Position)>,
+}
+
+/// A formula with its label and references resolved, ready for KaTeX.
+pub struct Resolved {
+    pub formula: String,
+    /// Number of the equation, if it has a label.
+    pub number: Option<u32>,
+}
+
+/// Part of a text, split around the uses of a command.
+pub enum Piece<'a> {
+    Text(&'a str),
+    /// The argument of the command.
+    Command(&'a str),
+}
+
+impl Equations {
+    /// Numbers the display formula at `position` if it has a label, which only
+    /// counts the first time it's used.
+    pub fn add(&mut self, formula: &str, position: Position) {
+        if let Ok((_, Some(label))) = take_label(formula) {
+            let number = self.labels.len() as u32 + 1;
+            self.labels.entry(label).or_insert((number, position));
+        }
+    }
+
+    /// Number of the equation with `label`.
+    pub fn number(&self, label: &str) -> Result<u32, String> {
+        self.labels
+            .get(label.trim())
+            .map(|&(number, _)| number)
+            .ok_or_else(|| format!("No equation has the label `{label}`"))
+    }
+
+    /// Replaces the label of the formula at `position` with its number, and
+    /// marks its references to make them links after rendering.
+    pub fn resolve(
+        &self,
+        formula: &str,
+        display_math: bool,
+        position: Position,
+    ) -> Result<Resolved, String> {
+        let (formula, label) = take_label(formula)?;
+        let number = match label {
+            None => None,
+            Some(_) if !display_math => {
+                return Err("Only display math can have a `\\label`".to_string());
+            }
+            Some(label) => match self.labels.get(&label) {
+                Some(&(number, first)) if first == position => Some(number),
+                _ => return Err(format!("Another equation has the label `{label}`")),
+            },
+        };
+
+        let mut resolved = String::new();
+        for piece in references(&formula)? {
+            match piece {
+                Piece::Text(text) => resolved.push_str(text),
+                Piece::Command(label) => {
+                    let number = self.number(label)?;
+                    write!(
+                        resolved,
+                        "\\text{{{REFERENCE_MARK}({number}){REFERENCE_MARK}}}"
+                    )
+                    .expect("Writing to a string can't fail");
+                }
+            }
+        }
+
+        if let Some(number) = number {
+            write!(resolved, " \\tag{{{number}}}").expect("Writing to a string can't fail");
+        }
+
+        Ok(Resolved {
+            formula: resolved,
+            number,
+        })
+    }
+}
+
+/// Id of the equation with `number`, for links to it.
+///
+/// The ids of headings never have a `:`, so they can't be the same.
+pub fn id(number: u32) -> String {
+    format!("eq:{number}")
+}
+
+/// Makes the references marked by [`Equations::resolve`] in the `html` of a
+/// formula rendered by KaTeX into links.
+///
+/// Only the visible part of the formula gets the links, the marks are just
+/// removed from the MathML for screen readers.
+///
+/// This depends on the shape of the HTML of KaTeX, which is checked by the
+/// tests in case an update of the `katex` crate changes it.
+pub fn link_references(html: &str) -> String {
+    let (mathml, visible) = html
+        .split_once("class=\"katex-html\"")
+        .unwrap_or(("", html));
+
+    let mut linked = mathml.replace(REFERENCE_MARK, "");
+    if !mathml.is_empty() {
+        linked.push_str("class=\"katex-html\"");
+    }
+    for (i, piece) in visible.split(REFERENCE_MARK).enumerate() {
+        // Marks come in pairs, so references are the odd pieces.
+        let number = piece
+            .strip_prefix('(')
+            .and_then(|piece| piece.strip_suffix(')'))
+            .and_then(|number| number.parse::<u32>().ok());
+        match number {
+            Some(number) if i % 2 == 1 => {
+                write!(linked, "<a href=\"#{}\">{piece}</a>", id(number))
+                    .expect("Writing to a string can't fail");
+            }
+            _ => linked.push_str(piece),
+        }
+    }
+
+    linked
+}
+
+/// Splits `text` around its `\eqref`s, whose arguments are labels.
+pub fn references(text: &str) -> Result<Vec<Piece<'_>>, String> {
+    split_command(text, "eqref")
+}
+
+/// Removes the `\label` of `formula`, returning it separately.
+fn take_label(formula: &str) -> Result<(String, Option<String>), String> {
+    let mut rest = String::new();
+    let mut label = None;
+    for piece in split_command(formula, "label")? {
+        match piece {
+            Piece::Text(text) => rest.push_str(text),
+            Piece::Command(_) if label.is_some() => {
+                return Err("An equation can only have one `\\label`".to_string());
+            }
+            Piece::Command(name) => label = Some(name.trim().to_string()),
+        }
+    }
+
+    Ok((rest, label))
+}
+
+/// Splits `text` around the uses of `\command{argument}`, where the argument
+/// can have braces as long as they're balanced.
+fn split_command<'a>(text: &'a str, command: &str) -> Result<Vec<Piece<'a>>, String> {
+    let start = format!("\\{command}{{");
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while let Some(index) = rest.find(&start) {
+        let after = &rest[index + start.len()..];
+        let mut depth = 0;
+        let end = after
+            .char_indices()
+            .find(|&(_, c)| match c {
+                '{' => {
+                    depth += 1;
+                    false
+                }
+                '}' if depth == 0 => true,
+                '}' => {
+                    depth -= 1;
+                    false
+                }
+                _ => false,
+            })
+            .map(|(end, _)| end)
+            .ok_or_else(|| format!("`\\{command}{{` is never closed with `}}`"))?;
+
+        pieces.push(Piece::Text(&rest[..index]));
+        pieces.push(Piece::Command(&after[..end]));
+        rest = &after[end + 1..];
+    }
+
+    pieces.push(Piece::Text(rest));
+    Ok(pieces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, entry, post};
+
+    /// [`link_references`] relies on how KaTeX renders formulas: the MathML
+    /// first and the visible HTML after `class="katex-html"`, with each marked
+    /// reference in a single piece of text. Updating the `katex` crate could
+    /// change that.
+    #[test]
+    fn katex_output_shape() {
+        let mut equations = Equations::default();
+        equations.add("a \\label{eq:a}", (1, 1));
+        let resolved = equations
+            .resolve("b = \\eqref{eq:a}", true, (2, 1))
+            .unwrap();
+
+        let opts = katex::Opts::builder().display_mode(true).build().unwrap();
+        let html = katex::render_with_opts(&resolved.formula, &opts).unwrap();
+        let (mathml, visible) = html
+            .split_once("class=\"katex-html\"")
+            .expect("KaTeX should render the visible formula in `.katex-html`");
+        assert!(mathml.contains("<math"), "The MathML should go first");
+        assert!(!visible.contains("<math"), "The MathML should go first");
+        let reference = format!("{REFERENCE_MARK}(1){REFERENCE_MARK}");
+        assert!(
+            visible.contains(&reference),
+            "The marks should be kept with the reference: {visible}"
+        );
+
+        let linked = link_references(&html);
+        assert!(!linked.contains(REFERENCE_MARK), "Marks should be removed");
+        assert_eq!(linked.matches("<a href=\"#eq:1\">(1)</a>").count(), 1);
+    }
+
+    #[test]
+    fn labels() {
+        let content = post(
+            "Math",
+            "",
+            "# Eq 1\n\n\
+             $$\na \\label{eq:a}\n$$\n\n\
+             $$\nb \\label{eq:a}\n$$\n\n\
+             Inline $c \\label{eq:c}$, see \\eqref{eq:a} and \\eqref{eq:missing}.\n\n\
+             $$\nd \\label{eq:d\n$$\n\n\
+             Unclosed \\eqref{eq:a.\n",
+        );
+
+        let entry = entry("math", &content, &testing::dev_config());
+        assert!(
+            entry
+                .html
+                .contains(r##"<span class="equation" id="eq:1">"##)
+        );
+        assert!(entry.html.contains(r##"<a href="#eq:1">(1)</a>"##));
+        assert!(
+            entry.html.contains(r#"<h2 id="eq-1">"#),
+            "Headings shouldn't have the ids of equations"
+        );
+
+        let warnings = entry.warnings.join("\n");
+        for expected in [
+            "Another equation has the label `eq:a`",
+            "Only display math can have a `\\label`",
+            "No equation has the label `eq:missing`",
+            "`\\label{` is never closed with `}`",
+            "`\\eqref{` is never closed with `}`",
+        ] {
+            assert!(
+                warnings.contains(expected),
+                "Missing {expected:?} in {warnings}"
+            );
+        }
+    }
+}
@@ -4,16 +4,30 @@
 //! not HTML. I think it's fine in practice.
 //!
 //! Checked for validity with https://validator.w3.org/feed/check.cgi
+//!
+//! Besides the feeds of the whole blog, there is a pair of feeds for each topic
+//! next to its page.
 
 use color_eyre::eyre;
 use jiff::{Timestamp, Zoned, fmt::rfc2822, tz::TimeZone};
 use maud::{Markup, PreEscaped, html};
 
-use crate::blog::BlogEntry;
+use crate::{
+    blog::{BlogEntry, topic_path},
+    config::SiteConfig,
+};
+
+/// Directory of the feeds of `topic`, or the whole blog.
+fn feed_dir(topic: Option<&str>) -> String {
+    match topic {
+        None => "blog".to_string(),
+        Some(topic) => topic_path(topic),
+    }
+}
 
 impl BlogEntry {
-    pub fn rss(&self) -> eyre::Result<Markup> {
-        let url = format!("https://odilf.com/blog/{}", self.slug);
+    pub fn rss(&self, config: &SiteConfig) -> eyre::Result<Markup> {
+        let url = config.url(&format!("blog/{}", self.slug));
 
         Ok(html! {
             item {
@@ -40,8 +54,8 @@ impl BlogEntry {
         Ok(Some(date))
     }
 
-    pub fn atom(&self) -> eyre::Result<Markup> {
-        let url = format!("https://odilf.com/blog/{}", self.slug);
+    pub fn atom(&self, config: &SiteConfig) -> eyre::Result<Markup> {
+        let url = config.url(&format!("blog/{}", self.slug));
 
         Ok(html! {
             entry {
@@ -60,46 +74,75 @@ impl BlogEntry {
     }
 }
 
-pub fn rss<'a>(entries: impl Iterator<Item = &'a BlogEntry>) -> eyre::Result<Markup> {
+/// Title and description of the feed of `topic`, or the whole blog.
+fn channel(config: &SiteConfig, topic: Option<&str>) -> (String, String) {
+    let feed = &config.blog.feed;
+    match topic {
+        None => (feed.title.clone(), feed.description.clone()),
+        Some(topic) => (
+            format!("{} (#{topic})", feed.title),
+            format!("{} Only the posts about {topic}.", feed.description),
+        ),
+    }
+}
+
+/// RSS feed with `entries`, which are the posts about `topic` if there is one.
+pub fn rss<'a>(
+    config: &SiteConfig,
+    topic: Option<&str>,
+    entries: impl Iterator<Item = &'a BlogEntry>,
+) -> eyre::Result<Markup> {
+    let feed = &config.blog.feed;
+    let (title, description) = channel(config, topic);
+    let dir = feed_dir(topic);
+
     Ok(html! {
         (PreEscaped(r#"<?xml version="1.0" encoding="UTF-8"?>"#))
         rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom" {
           channel {
-            title { "Odilf's blog" }
-            link { "https://odilf.com/blog" }
-            description { "Odilf's personal blog." }
-            language { "en" }
-            webMaster { "odysseas.maheras@gmail.com (Odilf)" }
+            title { (title) }
+            link { (config.url(&dir)) }
+            description { (description) }
+            language { (feed.language) }
+            webMaster { (config.author.email) " (" (config.author.name) ")" }
             lastBuildDate { (rfc2822::to_string(&Zoned::now())?) }
             generator { "Custom Generator at https://github.com/odilf/odilf.com" }
-            atom:link href="https://odilf.com/blog/rss.xml" rel="self" type="application/rss+xml" {}
+            atom:link href=(config.url(&format!("{dir}/rss.xml"))) rel="self" type="application/rss+xml" {}
 
             @for entry in entries {
-                (entry.rss()?)
+                (entry.rss(config)?)
             }
           }
         }
     })
 }
 
-pub fn atom<'a>(entries: impl Iterator<Item = &'a BlogEntry>) -> eyre::Result<Markup> {
+/// Atom feed with `entries`, which are the posts about `topic` if there is one.
+pub fn atom<'a>(
+    config: &SiteConfig,
+    topic: Option<&str>,
+    entries: impl Iterator<Item = &'a BlogEntry>,
+) -> eyre::Result<Markup> {
+    let (title, description) = channel(config, topic);
+    let dir = feed_dir(topic);
+
     Ok(html! {
         (PreEscaped(r#"<?xml version="1.0" encoding="UTF-8"?>"#))
         feed xmlns="http://www.w3.org/2005/Atom" {
-            title { "Odilf's blog" }
-            link href="https://odilf.com/blog" {}
-            link href="https://odilf.com/blog/atom.xml" rel="self" {}
-            id { "https://odilf.com/blog" }
+            title { (title) }
+            link href=(config.url(&dir)) {}
+            link href=(config.url(&format!("{dir}/atom.xml"))) rel="self" {}
+            id { (config.url(&dir)) }
             author {
-                name { "Odilf" }
-                email { "odysseas.maheras@gmail.com" }
+                name { (config.author.name) }
+                email { (config.author.email) }
             }
-            subtitle { "Odilf's personal blog." }
+            subtitle { (description) }
             updated { (Timestamp::now()) }
             generator uri="https://github.com/odilf/odilf.com" { "Custom Generator" }
 
             @for entry in entries {
-                (entry.atom()?)
+                (entry.atom(config)?)
             }
         }
     })
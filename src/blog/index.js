@@ -1,29 +1,17 @@
-let buttons = {
-  dev: document.getElementById("development-tab"),
-  personal: document.getElementById("personal-tab"),
-  all: document.getElementById("all-tab"),
-};
+const buttons = document.querySelectorAll("button[data-topic]");
 
-let active_tab = buttons.all;
+let active_tab = document.querySelector('button[data-topic=""]');
 
-for (const name in buttons) {
-  buttons[name].addEventListener("click", () => {
+for (const button of buttons) {
+  button.addEventListener("click", () => {
     active_tab.disabled = false;
-    active_tab = buttons[name];
+    active_tab = button;
     active_tab.disabled = true;
 
-    if (name === "all") {
-      document.querySelectorAll(".blog-entry").forEach((item) => {
-        item.style = "";
-      });
-    } else {
-      document.querySelectorAll(".blog-entry").forEach((item) => {
-        item.style = "display: none;";
-      });
-
-      document.querySelectorAll(`.topic-${name}`).forEach((item) => {
-        item.style = "";
-      });
-    }
+    const topic = button.dataset.topic;
+    document.querySelectorAll(".blog-entry").forEach((item) => {
+      const show = topic === "" || item.classList.contains(`topic-${topic}`);
+      item.style = show ? "" : "display: none;";
+    });
   });
 }
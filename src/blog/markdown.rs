@@ -1,35 +1,123 @@
-use crate::blog::BlogMetadata;
-use color_eyre::eyre::{self, ContextCompat, WrapErr as _};
+use crate::{
+    blog::{
+        BlogMetadata,
+        equations::{self, Equations, Piece},
+        toc::Heading,
+    },
+    front_matter,
+    highlight::{self, Fence},
+    report::LineError,
+};
+use color_eyre::eyre;
 use comrak::{
-    ExtensionOptions, Options, RenderOptions,
-    html::{ChildRendering, Context, format_document_with_formatter, format_node_default},
+    Anchorizer, ExtensionOptions, Options, RenderOptions,
+    html::{
+        ChildRendering, Context, collect_text, format_document_with_formatter, format_node_default,
+    },
     nodes::{AstNode, NodeValue},
     parse_document,
 };
-use gray_matter::{Matter, engine::YAML};
+use maud::{Markup, html};
 use std::{
+    collections::{BTreeMap, VecDeque},
     io::{self, Write},
     path::PathBuf,
 };
 
-pub fn parse_metadata(content: &str) -> eyre::Result<BlogMetadata> {
-    let frontmatter_parser = Matter::<YAML>::new();
-
-    let metadata = frontmatter_parser
-        .parse(content)
-        .wrap_err("Couldn't parse frontmatter")?;
-
-    metadata.data.wrap_err("Frontmatter not found")
+/// The front matter of a post, with warnings about its unknown fields.
+pub fn parse_metadata(content: &str) -> eyre::Result<(BlogMetadata, Vec<String>)> {
+    front_matter::parse(content)
 }
 
 pub struct MarkdownData {
     pub html: String,
     pub summary: String,
     pub word_count: u32,
+    /// Every heading of the post, in order.
+    pub headings: Vec<Heading>,
+    /// Whether there is any code block, which needs the highlighting stylesheet.
+    pub has_code: bool,
+    /// Whether there is any formula, which needs the KaTeX stylesheet.
+    pub has_math: bool,
+    /// Formulas that couldn't be rendered, which are shown as errors instead.
+    pub math_errors: Vec<MathError>,
+    /// Problems of the code blocks, which are rendered anyway.
+    pub code_warnings: Vec<String>,
+}
+
+/// A formula that KaTeX couldn't render.
+#[derive(Debug)]
+pub struct MathError {
+    /// Location of the formula in the file, starting at 1.
+    pub line: usize,
+    pub column: usize,
+    pub formula: String,
+    pub message: String,
+}
+
+/// Checks the formulas of the post with `slug` that couldn't be rendered,
+/// returning a warning for each one.
+///
+/// Release builds fail instead, while other builds carry on since the errors
+/// are shown in the page.
+pub fn check_math(slug: &str, errors: &[MathError], release: bool) -> eyre::Result<Vec<String>> {
+    let Some(first) = errors.first() else {
+        return Ok(Vec::new());
+    };
+
+    let problems = errors
+        .iter()
+        .map(|error| {
+            let formula = error
+                .formula
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            format!(
+                "`{formula}` at line {} column {}: {}",
+                error.line, error.column, error.message
+            )
+        })
+        .collect::<Vec<_>>();
+
+    if !release {
+        return Ok(problems
+            .into_iter()
+            .map(|problem| format!("Invalid math in {slug:?}, {problem}"))
+            .collect());
+    }
+
+    let error = LineError {
+        line: first.line,
+        column: Some(first.column),
+        // Aligned with the causes of the error report.
+        message: problems.join("\n     "),
+    };
+    Err(eyre::Report::new(error).wrap_err(format!("Invalid math in {slug:?}")))
+}
+
+/// State of the rendering of a post.
+#[derive(Default)]
+struct RenderState {
+    /// Ids of the headings that are left, in the order they are rendered.
+    heading_ids: VecDeque<String>,
+    /// Id of the heading being rendered, for its permalink.
+    current_heading: Option<String>,
+    /// KaTeX macros of the post, by name.
+    macros: BTreeMap<String, String>,
+    equations: Equations,
+    math_errors: Vec<MathError>,
+    code_warnings: Vec<String>,
 }
 
 /// Parses the input into markdown and returns an `(html, summary)` tuple.
-pub fn parse(input: &str, referenced_links: &mut Vec<String>) -> MarkdownData {
+///
+/// Formulas can use the KaTeX `macros`.
+pub fn parse(
+    input: &str,
+    referenced_links: &mut Vec<String>,
+    macros: &BTreeMap<String, String>,
+) -> MarkdownData {
     // TODO: Pass this arena from above.
     let arena = comrak::Arena::new();
 
@@ -86,10 +174,38 @@ pub fn parse(input: &str, referenced_links: &mut Vec<String>) -> MarkdownData {
     };
 
     let mut word_count = 0;
+    let mut has_code = false;
+    let mut has_math = false;
+    let mut headings = Vec::new();
+    let mut anchorizer = Anchorizer::new();
+    let mut equations = Equations::default();
     for node in root.descendants() {
+        let start = node.data.borrow().sourcepos.start;
         match &mut node.data.borrow_mut().value {
             // Increase the levels of all heading by one, since the title is going to be the first.
-            NodeValue::Heading(heading) => heading.level += 1,
+            NodeValue::Heading(heading) => {
+                heading.level += 1;
+
+                // The heading itself is borrowed, so the text is collected from its children.
+                let mut title = Vec::new();
+                for child in node.children() {
+                    collect_text(child, &mut title);
+                }
+                let title = String::from_utf8(title).expect("Markdown is valid UTF-8");
+                headings.push(Heading {
+                    level: heading.level,
+                    id: anchorizer.anchorize(title.clone()),
+                    title,
+                });
+            }
+            NodeValue::CodeBlock(_) => has_code = true,
+            NodeValue::Math(math) => {
+                has_math = true;
+                // Numbered before rendering, so that references can come before the equation.
+                if math.display_math {
+                    equations.add(&math.literal, (start.line, start.column));
+                }
+            }
             NodeValue::Image(img) => {
                 referenced_links.push(img.url.clone());
                 img.url = PathBuf::from("/blog")
@@ -107,13 +223,18 @@ pub fn parse(input: &str, referenced_links: &mut Vec<String>) -> MarkdownData {
     }
 
     let mut html = vec![];
-    format_document_with_formatter(
+    let state = format_document_with_formatter(
         root,
         &options,
         &mut html,
         &comrak::Plugins::default(),
         format_node_custom,
-        (),
+        RenderState {
+            heading_ids: headings.iter().map(|heading| heading.id.clone()).collect(),
+            macros: macros.clone(),
+            equations,
+            ..Default::default()
+        },
     )
     .expect("Markdown should be well-formed.");
     let html = String::from_utf8(html).expect("Parsing should generate valid UTF-8");
@@ -122,23 +243,97 @@ pub fn parse(input: &str, referenced_links: &mut Vec<String>) -> MarkdownData {
         html,
         summary,
         word_count,
+        headings,
+        has_code,
+        has_math,
+        math_errors: state.math_errors,
+        code_warnings: state.code_warnings,
     }
 }
 
 #[inline]
 fn format_node_custom<'a>(
-    context: &mut Context,
+    context: &mut Context<RenderState>,
     node: &'a AstNode<'a>,
     entering: bool,
 ) -> io::Result<ChildRendering> {
     match node.data.borrow().value {
         NodeValue::Math(_) => render_math(context, node, entering),
+        NodeValue::Heading(_) => render_heading(context, node, entering),
+        NodeValue::CodeBlock(_) => render_code_block(context, node, entering),
+        NodeValue::Text(ref text) if text.contains("\\eqref{") => {
+            render_references(context, node, entering)
+        }
         _ => format_node_default(context, node, entering),
     }
 }
 
+/// Renders headings with an id and a permalink to it, which is shown on hover.
+fn render_heading<'a>(
+    context: &mut Context<RenderState>,
+    node: &'a AstNode<'a>,
+    entering: bool,
+) -> io::Result<ChildRendering> {
+    let NodeValue::Heading(ref heading) = node.data.borrow().value else {
+        panic!()
+    };
+
+    if entering {
+        let id = context
+            .user
+            .heading_ids
+            .pop_front()
+            .expect("Every heading has an id");
+        context.cr()?;
+        write!(context, "<h{} id=\"{id}\">", heading.level)?;
+        context.user.current_heading = Some(id);
+    } else {
+        let id = context
+            .user
+            .current_heading
+            .take()
+            .expect("Heading was entered");
+        writeln!(
+            context,
+            "<a class=\"heading-anchor\" href=\"#{id}\" aria-label=\"Link to this section\">#</a></h{}>",
+            heading.level
+        )?;
+    }
+
+    Ok(ChildRendering::HTML)
+}
+
+/// Renders code blocks highlighted, with the options of their fence.
+fn render_code_block<'a>(
+    context: &mut Context<RenderState>,
+    node: &'a AstNode<'a>,
+    entering: bool,
+) -> io::Result<ChildRendering> {
+    let NodeValue::CodeBlock(ref code_block) = node.data.borrow().value else {
+        panic!()
+    };
+
+    if entering {
+        let mut warnings = Vec::new();
+        let fence = Fence::parse(&code_block.info, &mut warnings);
+        let html = highlight::render(&code_block.literal, &fence, &mut warnings);
+
+        let line = node.data.borrow().sourcepos.start.line;
+        context.user.code_warnings.extend(
+            warnings
+                .into_iter()
+                .map(|warning| format!("Code block at line {line}: {warning}")),
+        );
+
+        context.cr()?;
+        writeln!(context, "{}", html.0)?;
+    }
+
+    Ok(ChildRendering::HTML)
+}
+
 fn render_math<'a>(
-    context: &mut Context,
+    context: &mut Context<RenderState>,
     node: &'a AstNode<'a>,
     entering: bool,
 ) -> io::Result<ChildRendering> {
@@ -155,16 +350,232 @@ fn render_math<'a>(
     };
 
     if entering {
-        // TODO: Handle errors.
-        let opts = katex::Opts::builder()
-            .display_mode(display_math)
-            .build()
-            .unwrap();
-        let html = katex::render_with_opts(literal, &opts).unwrap();
-
-        // TODO: This sometimes overflows uglily
-        write!(context, "{html}")?;
+        let start = node.data.borrow().sourcepos.start;
+        let rendered = context
+            .user
+            .equations
+            .resolve(literal, display_math, (start.line, start.column))
+            .and_then(|resolved| {
+                let mut opts = katex::Opts::builder();
+                for (name, definition) in &context.user.macros {
+                    opts = opts.add_macro(name.clone(), definition.clone());
+                }
+
+                let opts = opts
+                    .display_mode(display_math)
+                    .build()
+                    .map_err(|err| err.to_string())?;
+                let html =
+                    katex::render_with_opts(&resolved.formula, &opts).map_err(|err| match err {
+                        katex::Error::JsExecError(details) => katex_message(&details),
+                        err => err.to_string(),
+                    })?;
+                Ok((equations::link_references(&html), resolved.number))
+            });
+
+        match rendered {
+            // TODO: This sometimes overflows uglily
+            Ok((html, None)) => write!(context, "{html}")?,
+            Ok((html, Some(number))) => write!(
+                context,
+                "<span class=\"equation\" id=\"{}\">{html}</span>",
+                equations::id(number)
+            )?,
+            Err(message) => {
+                write!(context, "{}", math_error(literal, &message, display_math).0)?;
+
+                context.user.math_errors.push(MathError {
+                    line: start.line,
+                    column: start.column,
+                    formula: literal.clone(),
+                    message,
+                });
+            }
+        }
+    }
+
+    Ok(ChildRendering::HTML)
+}
+
+/// Renders text with `\eqref`s, which become links to the equations.
+fn render_references<'a>(
+    context: &mut Context<RenderState>,
+    node: &'a AstNode<'a>,
+    entering: bool,
+) -> io::Result<ChildRendering> {
+    let NodeValue::Text(ref text) = node.data.borrow().value else {
+        panic!()
+    };
+
+    if entering {
+        let mut rendered = String::new();
+        let mut errors = Vec::new();
+        match equations::references(text) {
+            Ok(pieces) => {
+                for piece in pieces {
+                    let html = match piece {
+                        Piece::Text(text) => html! { (text) },
+                        Piece::Command(label) => match context.user.equations.number(label) {
+                            Ok(number) => html! {
+                                a href=(format!("#{}", equations::id(number))) { "(" (number) ")" }
+                            },
+                            Err(message) => {
+                                let reference = format!("\\eqref{{{label}}}");
+                                let html = math_error(&reference, &message, false);
+                                errors.push((reference, message));
+                                html
+                            }
+                        },
+                    };
+                    rendered.push_str(&html.0);
+                }
+            }
+            Err(message) => {
+                rendered.push_str(&math_error(text, &message, false).0);
+                errors.push((text.to_string(), message));
+            }
+        }
+
+        let start = node.data.borrow().sourcepos.start;
+        for (formula, message) in errors {
+            context.user.math_errors.push(MathError {
+                line: start.line,
+                column: start.column,
+                formula,
+                message,
+            });
+        }
+        write!(context, "{rendered}")?;
     }
 
     Ok(ChildRendering::HTML)
 }
+
+/// The message of a KaTeX error, from the details of the exception.
+///
+/// They come as a debug formatted JS value (e.g. `String("ParseError: ...")`),
+/// with the position of the error underlined with combining characters.
+fn katex_message(details: &str) -> String {
+    let message = details
+        .strip_prefix("String(\"")
+        .and_then(|details| details.strip_suffix("\")"))
+        .map_or_else(|| details.to_string(), unescape);
+    let message = message.strip_prefix("ParseError: ").unwrap_or(&message);
+
+    message
+        .replace('\u{332}', "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Undoes the escapes of a debug formatted string.
+fn unescape(escaped: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = escaped.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('u') => {
+                let code = chars
+                    .by_ref()
+                    .skip(1)
+                    .take_while(|&c| c != '}')
+                    .collect::<String>();
+                if let Some(c) = u32::from_str_radix(&code, 16).ok().and_then(char::from_u32) {
+                    unescaped.push(c);
+                }
+            }
+            Some(c) => unescaped.push(c),
+            None => (),
+        }
+    }
+
+    unescaped
+}
+
+/// Visible error in place of a formula that couldn't be rendered.
+fn math_error(formula: &str, message: &str, display_math: bool) -> Markup {
+    html! {
+        span."math-error"."math-error-display"[display_math] role="alert" {
+            code { (formula) }
+            " "
+            span."math-error-message" { (message) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        blog::BlogEntry,
+        testing::{self, entry, post},
+    };
+
+    #[test]
+    fn code_block_warnings() {
+        let content = post("Code", "", "```nosuchlang hl=x nubmers\ncode\n```\n");
+        let entry = entry("code", &content, &testing::config());
+        assert_eq!(
+            entry.warnings,
+            [
+                "Code block at line 6: invalid lines to highlight `x`, they should be like `2,4-6`",
+                "Code block at line 6: unknown option `nubmers`",
+                "Code block at line 6: unknown language `nosuchlang`, it isn't highlighted",
+            ]
+        );
+    }
+
+    #[test]
+    fn math_errors() {
+        let content = post("Math", "", "Oops: $\\frac{1}$.\n");
+
+        let entry = entry("math", &content, &testing::dev_config());
+        assert!(
+            entry
+                .html
+                .contains(r#"<span class="math-error" role="alert"><code>\frac{1}</code>"#),
+            "Dev builds show math errors in the page"
+        );
+        assert_eq!(
+            entry.warnings.len(),
+            1,
+            "Math errors should be warned about"
+        );
+
+        let err =
+            BlogEntry::from_slug_and_content("math", &content, &testing::config()).unwrap_err();
+        let report = format!("{err:?}");
+        assert!(
+            report.contains(r#"Invalid math in "math""#)
+                && report.contains("`\\frac{1}` at line 6 column 7"),
+            "Unexpected error: {report}"
+        );
+    }
+
+    #[test]
+    fn math_is_untrusted() {
+        let content = post(
+            "Math",
+            "",
+            "$$\na = \\eqref{eq:a} \\label{eq:a}\n$$\n\n\
+             Inline $\\href{https://example.com}{b}$.\n",
+        );
+
+        let entry = entry("math", &content, &testing::config());
+        assert!(
+            !entry.html.contains(r#"href="https://example.com""#),
+            "Formulas shouldn't be able to make links"
+        );
+        assert!(
+            entry.html.contains(r##"<a href="#eq:1">(1)</a>"##),
+            "References in formulas should still be links"
+        );
+    }
+}
@@ -1,95 +1,327 @@
 //! Blog of odilf.com
 
+pub mod archive;
+pub mod card;
+mod equations;
 pub mod feed;
 mod markdown;
+mod related;
+pub mod series;
+mod toc;
 
 use std::borrow::Cow;
 
 use crate::{
+    PageMeta,
     blog::markdown::MarkdownData,
-    components::{self, back, tag},
+    cache::Hash,
+    collection::{
+        Collection, DerivedOutput, EntryLink, EntryOutput, ExtraOutput, Neighbours, SeriesLinks,
+    },
+    components::{self, back, back_to, tag},
+    config::SiteConfig,
+    front_matter::{Field, FrontMatter},
+    search,
 };
-use color_eyre::eyre;
+use color_eyre::eyre::{self, ContextCompat as _};
 use maud::{Markup, PreEscaped, Render, html};
 use serde::{Deserialize, Serialize};
+use std::{cmp::Reverse, collections::BTreeMap, num::NonZeroU32, path::Path};
 
 pub const DESC: &str = "some thoughts, stories and reflections from throughout the years.";
 
-/// Blog home page, with the blog entries.
-pub fn home<'a>(entries: impl Iterator<Item = &'a BlogEntry>) -> Markup {
-    // Show the drafts with less opacity on development.
-    #[cfg(debug_assertions)]
-    const STYLE_IF_DEBUG: &str = r"<style> .draft-post { opacity: 50%; } </style>";
-    #[cfg(not(debug_assertions))]
-    const STYLE_IF_DEBUG: &str = "";
+/// A page of the blog home.
+#[derive(Debug, Clone, Copy)]
+pub struct Page {
+    /// Starting at 1.
+    pub number: usize,
+    /// Number of posts in each page.
+    pub size: usize,
+}
+
+impl Page {
+    /// The first page, with the size set in `config`.
+    pub fn first(config: &SiteConfig) -> Self {
+        Self {
+            number: 1,
+            size: config.blog.posts_per_page.max(1),
+        }
+    }
+
+    /// Path of the page with `number`, from the root of the site.
+    pub fn href(number: usize) -> String {
+        match number {
+            1 => "/blog/".to_string(),
+            number => format!("/blog/page/{number}/"),
+        }
+    }
+
+    /// Number of pages needed for `posts`.
+    pub fn count(self, posts: usize) -> usize {
+        posts.div_ceil(self.size).max(1)
+    }
+}
+
+/// Blog home page, with the posts of `page` of the `entries`.
+///
+/// The topic tabs are made from all the entries, but they only filter the
+/// posts of this page.
+pub fn home<'a>(entries: impl Iterator<Item = &'a BlogEntry> + Clone, page: Page) -> Markup {
+    let pages = page.count(entries.clone().count());
 
     html! {
-        (back())
+        @if page.number == 1 {
+            (back())
+        } @else {
+            (back_to(&Page::href(page.number - 1)))
+        }
 
         h1 { "blog" }
         p."pb-4 faint" { (DESC) }
 
-        ."flex mb-4 gap-2" {
-            button #all-tab disabled="true" { "all" }
-            button #development-tab { "development" }
-            button #personal-tab { "personal" }
+        ."flex flex-wrap mb-4 gap-2" {
+            // No slug is empty (see `check_slugs`), so it can't be a topic.
+            button data-topic="" disabled="true" { "all" }
+            @for (topic, count) in topics(entries.clone()) {
+                button data-topic=(topic_slug(topic)) { (topic) " (" (count) ")" }
+            }
+            ."flex-1" {}
+            a."faint self-center" href="/blog/topics/" { "all topics" }
+            a."faint self-center" href="/blog/series/" { "series" }
+            a."faint self-center" href="/blog/archive/" { "archive" }
         }
 
         ul {
-            @for entry in entries {
+            @for entry in entries.skip((page.number - 1) * page.size).take(page.size) {
                 li."mb-4" { (entry.render_summary()) }
             }
         }
 
+        @if pages > 1 {
+            nav."flex gap-2 mb-8" {
+                @if page.number > 1 {
+                    a href=(Page::href(page.number - 1)) { "<-- newer" }
+                }
+                ."flex-1 text-center faint" { "page " (page.number) " of " (pages) }
+                @if page.number < pages {
+                    a href=(Page::href(page.number + 1)) { "older -->" }
+                }
+            }
+        }
+
         script {
             (PreEscaped(include_str!("./index.js")))
         }
+    }
+}
+
+/// The topics of `entries`, with the number of entries of each one, from the
+/// most common to the least.
+pub fn topics<'a>(entries: impl Iterator<Item = &'a BlogEntry>) -> Vec<(&'a str, usize)> {
+    let mut counts = BTreeMap::<&str, usize>::new();
+    for topic in entries.flat_map(|entry| &entry.metadata.topics) {
+        *counts.entry(topic).or_default() += 1;
+    }
 
-        (PreEscaped(STYLE_IF_DEBUG))
+    let mut topics = counts.into_iter().collect::<Vec<_>>();
+    topics.sort_by_key(|&(topic, count)| (Reverse(count), topic));
+    topics
+}
+
+/// Version of `topic` that can be used in URLs and classes.
+pub fn topic_slug(topic: &str) -> String {
+    let mut slug = String::with_capacity(topic.len());
+    for c in topic.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else {
+            slug.push('-');
+        }
+    }
+    slug
+}
+
+/// Checks that the `names` of the topics or series (`kind`) have different
+/// slugs, since their pages would overwrite each other otherwise, and that
+/// none of them is empty.
+fn check_slugs<'a>(kind: &str, names: impl IntoIterator<Item = &'a str>) -> eyre::Result<()> {
+    let mut slugs = BTreeMap::new();
+    for name in names {
+        let slug = topic_slug(name);
+        if slug.is_empty() {
+            eyre::bail!("The {kind} can't have an empty name");
+        }
+        if let Some(other) = slugs.insert(slug.clone(), name)
+            && other != name
+        {
+            eyre::bail!(
+                "The {kind} {other:?} and {name:?} would have the same page at \
+                 /blog/{kind}/{slug}/, rename one of them"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Directory of the page of `topic`, relative to the output directory.
+pub fn topic_path(topic: &str) -> String {
+    format!("blog/topics/{}", topic_slug(topic))
+}
+
+/// The `entries` that go in the feeds, without the drafts and scheduled posts
+/// (which are only there when they're built).
+fn public<'a>(
+    entries: impl IntoIterator<Item = &'a BlogEntry>,
+) -> impl Iterator<Item = &'a BlogEntry> {
+    entries.into_iter().filter(|entry| entry.is_public())
+}
+
+pub(crate) fn posts(count: usize) -> String {
+    match count {
+        1 => "1 post".to_string(),
+        count => format!("{count} posts"),
+    }
+}
+
+/// Page with the posts about `topic`.
+pub fn topic_page(topic: &str, entries: &[&BlogEntry]) -> Markup {
+    html! {
+        (back())
+
+        h1 { "#" (topic) }
+        p."pb-4 faint" {
+            (posts(entries.len())) " about " (topic) ", also available as "
+            a href="rss.xml" { "RSS" } " and " a href="atom.xml" { "Atom" } " feeds."
+        }
+
+        ul {
+            @for entry in entries {
+                li."mb-4" { (entry.render_summary()) }
+            }
+        }
+    }
+}
+
+/// Page with every topic of the blog.
+pub fn topics_page(topics: &[(&str, usize)]) -> Markup {
+    html! {
+        (back())
+
+        h1 { "topics" }
+        p."pb-4 faint" { "everything the blog talks about." }
+
+        ul."flex flex-col gap-2" {
+            @for (topic, count) in topics {
+                li."text-lg" {
+                    a."text-secondary" href=(format!("/{}/", topic_path(topic))) { "#" (topic) }
+                    span."faint" { " (" (posts(*count)) ")" }
+                }
+            }
+        }
     }
 }
 
 /// An entry in the blog.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlogEntry {
     pub slug: String,
     pub html: String,
     pub summary: String,
     pub word_count: u32,
+    pub headings: Vec<toc::Heading>,
+    /// Whether the post has code blocks.
+    pub has_code: bool,
+    /// Whether the post has math.
+    pub has_math: bool,
     pub metadata: BlogMetadata,
+    /// Status of the post in this build, which is [`PostStatus::Published`]
+    /// for scheduled posts once their date has passed.
+    pub status: PostStatus,
+    /// Local files referenced by the post (e.g. images), relative to the blog directory.
+    pub assets: Vec<String>,
+    /// Problems of the post that don't stop it from being built.
+    pub warnings: Vec<String>,
 }
 
 impl BlogEntry {
+    /// Parses the blog entry, returning `None` if it's not published yet
+    /// (a draft, or scheduled for later) and drafts are not included in the
+    /// build.
+    ///
+    /// Formulas that can't be rendered are shown as errors in the page, except
+    /// in release builds, which fail instead.
     pub fn from_slug_and_content(
         slug: impl Into<String>,
         content: &str,
-        referenced_links: &mut Vec<String>,
+        config: &SiteConfig,
     ) -> eyre::Result<Option<Self>> {
-        let Ok(metadata) = markdown::parse_metadata(content) else {
-            return Ok(None);
+        let options = &config.build;
+        let (metadata, mut warnings) = markdown::parse_metadata(content)?;
+        if metadata.part.is_some() && metadata.series.is_none() {
+            eyre::bail!("Only posts in a `series` can have a `part`");
+        }
+
+        let status = match metadata.status()? {
+            PostStatus::Scheduled => {
+                let date = metadata.date.wrap_err("Scheduled posts need a `date`")?;
+                if date <= options.today {
+                    PostStatus::Published
+                } else {
+                    PostStatus::Scheduled
+                }
+            }
+            status => status,
         };
 
-        #[cfg(not(debug_assertions))]
-        if metadata.draft != Some(false) {
-            tracing::debug!("Skipped draft post");
+        if !options.drafts && !status.is_public() {
+            tracing::debug!(?status, "Skipped unpublished post");
             return Ok(None);
         }
 
+        // The macros of the post take precedence over the global ones.
+        let mut macros = config.blog.math_macros.clone();
+        macros.extend(metadata.math_macros.clone());
+
+        let mut assets = Vec::new();
         let MarkdownData {
             html,
             summary,
             word_count,
-        } = markdown::parse(content, referenced_links);
+            headings,
+            has_code,
+            has_math,
+            math_errors,
+            code_warnings,
+        } = markdown::parse(content, &mut assets, &macros);
+
+        let slug = slug.into();
+        warnings.extend(code_warnings);
+        warnings.extend(markdown::check_math(&slug, &math_errors, options.release)?);
 
         Ok(Some(Self {
-            slug: slug.into(),
+            slug,
             html,
             summary,
             word_count,
+            headings,
+            has_code,
+            has_math,
             metadata,
+            status,
+            assets,
+            warnings,
         }))
     }
 
+    /// The summary as a single line, for descriptions in link previews.
+    pub fn description(&self) -> String {
+        self.summary
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
     pub fn tags(&self) -> impl Iterator<Item = Cow<'_, str>> {
         use std::iter::once;
         self.metadata
@@ -101,6 +333,28 @@ impl BlogEntry {
                 self.word_count as f32 / 1000.0
             ))))
     }
+
+    /// Explanation of the status of the post, shown at the top of its page
+    /// unless it's published or unlisted.
+    pub fn status_notice(&self) -> Option<String> {
+        let notice = match self.status {
+            PostStatus::Published | PostStatus::Unlisted => return None,
+            PostStatus::Draft => "This is a draft, it's not published yet.".to_string(),
+            PostStatus::Scheduled => match self.metadata.date {
+                Some(date) => format!(
+                    "This post is scheduled to be published on {}.",
+                    date.strftime("%d %b, %Y")
+                ),
+                None => "This post is scheduled to be published.".to_string(),
+            },
+            PostStatus::Archived => {
+                "This post is archived, it's kept for reference but might be outdated.".to_string()
+            }
+        };
+
+        Some(notice)
+    }
+
     pub const fn num_tags(&self) -> usize {
         self.metadata.topics.len() + 1
     }
@@ -110,12 +364,12 @@ impl BlogEntry {
         for topic in &self.metadata.topics {
             topic_classes.push(' ');
             topic_classes.push_str("topic-");
-            topic_classes.push_str(topic);
+            topic_classes.push_str(&topic_slug(topic));
         }
 
-        #[cfg(debug_assertions)]
-        if self.metadata.draft != Some(false) {
-            topic_classes.push_str(" draft-post")
+        if let Some(class) = self.status.class() {
+            topic_classes.push(' ');
+            topic_classes.push_str(class);
         }
 
         html! {
@@ -131,6 +385,10 @@ impl BlogEntry {
                     }
                 }
 
+                @if self.status != PostStatus::Published {
+                    ."text-sm text-secondary" { (self.status.name()) }
+                }
+
                 ."flex gap-2 text-primary no-no-underline" {
                     ."flex-1 text-sm opacity-50 line-clamp-2 text-ellipsis" {
                         (self.summary)
@@ -152,11 +410,11 @@ impl BlogEntry {
             }
         }
     }
-}
 
-impl Render for BlogEntry {
-    fn render(&self) -> Markup {
+    /// The post, with the parts of its `series` if it's in one.
+    pub fn render_in_series(&self, series: Option<&SeriesLinks>) -> Markup {
         let number_headings = self.metadata.numbered_headings.unwrap_or(true);
+        let numbered_from = format!("numbered-from-h{}", toc::top_level(&self.headings));
 
         html! {
             (components::back())
@@ -172,18 +430,263 @@ impl Render for BlogEntry {
                     (tag(tag_text))
                 }
             }
-            ."prose pb-8"."numbered-headings"[number_headings] lang=(self.metadata.lang.html_name()) {
+            @if let Some(notice) = self.status_notice() {
+                ."status-banner mb-6" { (notice) }
+            }
+            @if let Some(series) = series {
+                (series::render_box(series))
+            }
+            @if self.metadata.toc && !self.headings.is_empty() {
+                (toc::render(&self.headings, number_headings))
+            }
+            ."prose pb-8"."numbered-headings"[number_headings].(numbered_from)[number_headings]
+                lang=(self.metadata.lang.html_name()) {
                 (PreEscaped(&self.html))
             }
+        }
+    }
+}
+
+/// Related posts and links to the newer and older ones, for the end of a post.
+pub fn render_neighbours(neighbours: &Neighbours) -> Markup {
+    let link = |link: &EntryLink| {
+        html! {
+            a href=(link.href) { (link.title) }
+            @if let Some(date) = link.date {
+                span."faint text-sm" { " (" (date.strftime("%d %b, %Y")) ")" }
+            }
+        }
+    };
+
+    html! {
+        @if !neighbours.related.is_empty() {
+            section."mb-6" {
+                h2."text-xl mb-2" { "related" }
+                ul {
+                    @for related in &neighbours.related {
+                        li { "> " (link(related)) }
+                    }
+                }
+            }
+        }
+
+        @if neighbours.previous.is_some() || neighbours.next.is_some() {
+            nav."flex gap-4 mb-8" {
+                ."flex-1" {
+                    @if let Some(newer) = &neighbours.previous {
+                        ."faint text-sm" { "<-- newer" }
+                        (link(newer))
+                    }
+                }
+                ."flex-1 text-right" {
+                    @if let Some(older) = &neighbours.next {
+                        ."faint text-sm" { "older -->" }
+                        (link(older))
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Render for BlogEntry {
+    fn render(&self) -> Markup {
+        self.render_in_series(None)
+    }
+}
+
+impl Collection for BlogEntry {
+    const NAME: &'static str = "blog post";
+    const PATH: &'static str = "blog";
+    const EXTRA_OUTPUTS: &'static [ExtraOutput<Self>] = &[
+        ExtraOutput {
+            path: "blog/rss.xml",
+            render: |config, entries| Ok(feed::rss(config, None, public(entries))?.0),
+        },
+        ExtraOutput {
+            path: "blog/atom.xml",
+            render: |config, entries| Ok(feed::atom(config, None, public(entries))?.0),
+        },
+    ];
+    const ENTRY_OUTPUTS: &'static [EntryOutput<Self>] = &[EntryOutput {
+        file_name: card::FILE_NAME,
+        inputs: |entry| Hash::of(&(&entry.metadata, entry.word_count)),
+        render: Self::render_card,
+    }];
 
-            link
-                rel="stylesheet"
-                href="https://cdn.jsdelivr.net/npm/katex@0.16.21/dist/katex.min.css"
-                integrity="sha384-zh0CIslj+VczCZtlzBcjt5ppRcsAmDnRem7ESsYwWwg3m/OaJ2l4x7YBZl9Kxxib"
-                crossorigin="anonymous"
-            {}
+    fn source_dir(config: &SiteConfig) -> &Path {
+        &config.blog.path
+    }
+
+    fn parse(slug: &str, content: &str, config: &SiteConfig) -> eyre::Result<Option<Self>> {
+        Self::from_slug_and_content(slug, content, config)
+    }
+
+    /// Whether the post is past its date, if it's scheduled, so that it's
+    /// parsed again when it's published.
+    fn cache_inputs(content: &str, config: &SiteConfig) -> impl Serialize {
+        let (metadata, _) = markdown::parse_metadata(content).ok()?;
+        let is_scheduled = matches!(metadata.status(), Ok(PostStatus::Scheduled));
+        let date = metadata.date.filter(|_| is_scheduled)?;
+        Some(date <= config.build.today)
+    }
+
+    fn slug(&self) -> &str {
+        &self.slug
+    }
+
+    fn link(&self) -> EntryLink {
+        EntryLink {
+            href: format!("/blog/{}", self.slug),
+            title: self.metadata.title.clone(),
+            date: self.metadata.date,
         }
     }
+
+    fn date(&self) -> Option<jiff::civil::Date> {
+        self.metadata.date
+    }
+
+    fn sort_key(&self) -> impl Ord {
+        (Reverse(self.metadata.date), &self.slug)
+    }
+
+    fn assets(&self) -> &[String] {
+        &self.assets
+    }
+
+    fn is_listed(&self) -> bool {
+        self.status != PostStatus::Unlisted
+    }
+
+    fn is_public(&self) -> bool {
+        self.status.is_public()
+    }
+
+    fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    fn search_document(&self) -> Option<search::Document> {
+        Some(search::Document {
+            url: format!("/blog/{}", self.slug),
+            title: self.metadata.title.clone(),
+            kind: "blog post".to_string(),
+            date: self.metadata.date,
+            summary: self.description(),
+            keywords: self.metadata.topics.clone(),
+            body: self.html.clone(),
+        })
+    }
+
+    fn meta(&self) -> PageMeta {
+        PageMeta {
+            title: Some(self.metadata.title.clone()),
+            description: Some(self.description()),
+            image: Some(self.card_path()),
+            date: self.date(),
+            article: true,
+            math: self.has_math,
+            code: self.has_code,
+            ..Default::default()
+        }
+    }
+
+    fn render_page(&self, neighbours: &Neighbours) -> Markup {
+        html! {
+            (self.render_in_series(neighbours.series.as_ref()))
+            (render_neighbours(neighbours))
+        }
+    }
+
+    fn related(entries: &[&Self]) -> Vec<Vec<usize>> {
+        related::related(entries)
+    }
+
+    fn series(entries: &[&Self]) -> Vec<Option<SeriesLinks>> {
+        series::links(entries)
+    }
+
+    fn conflicts(entries: &[Self]) -> Vec<(usize, eyre::Report)> {
+        series::duplicate_parts(entries)
+    }
+
+    fn index_meta() -> PageMeta {
+        PageMeta::titled("blog", DESC)
+    }
+
+    fn render_index(config: &SiteConfig, entries: &[Self]) -> Markup {
+        home(entries.iter(), Page::first(config))
+    }
+
+    fn derived_outputs(config: &SiteConfig, entries: &[Self]) -> eyre::Result<Vec<DerivedOutput>> {
+        let first = Page::first(config);
+        let mut outputs = (2..=first.count(entries.len()))
+            .map(|number| DerivedOutput::Page {
+                path: format!("blog/page/{number}/index.html"),
+                meta: PageMeta::titled(format!("blog (page {number})"), DESC),
+                content: home(entries.iter(), Page { number, ..first }),
+            })
+            .collect::<Vec<_>>();
+
+        outputs.extend(archive::outputs(entries));
+
+        let all_series = series::series(entries);
+        check_slugs("series", all_series.iter().map(|series| series.name))?;
+        outputs.push(DerivedOutput::Page {
+            path: "blog/series/index.html".to_string(),
+            meta: PageMeta::titled("series", series::DESC),
+            content: series::overview(&all_series),
+        });
+        for series in &all_series {
+            outputs.push(DerivedOutput::Page {
+                path: format!("{}/index.html", series.path()),
+                meta: PageMeta {
+                    date: series.parts.iter().filter_map(|entry| entry.date()).max(),
+                    ..PageMeta::titled(
+                        series.name,
+                        format!("a series of {}.", posts(series.parts.len())),
+                    )
+                },
+                content: series::page(series),
+            });
+        }
+
+        let topics = topics(entries.iter());
+        check_slugs("topics", topics.iter().map(|&(topic, _)| topic))?;
+        outputs.push(DerivedOutput::Page {
+            path: "blog/topics/index.html".to_string(),
+            meta: PageMeta::titled("topics", "everything the blog talks about."),
+            content: topics_page(&topics),
+        });
+
+        for &(topic, _) in &topics {
+            let with_topic = entries
+                .iter()
+                .filter(|entry| entry.metadata.topics.iter().any(|t| t == topic))
+                .collect::<Vec<_>>();
+            let dir = topic_path(topic);
+
+            outputs.push(DerivedOutput::Page {
+                path: format!("{dir}/index.html"),
+                meta: PageMeta {
+                    date: with_topic.iter().filter_map(|entry| entry.date()).max(),
+                    ..PageMeta::titled(format!("#{topic}"), format!("posts about {topic}."))
+                },
+                content: topic_page(topic, &with_topic),
+            });
+            outputs.push(DerivedOutput::File {
+                path: format!("{dir}/rss.xml"),
+                content: feed::rss(config, Some(topic), public(with_topic.iter().copied()))?.0,
+            });
+            outputs.push(DerivedOutput::File {
+                path: format!("{dir}/atom.xml"),
+                content: feed::atom(config, Some(topic), public(with_topic.iter().copied()))?.0,
+            });
+        }
+
+        Ok(outputs)
+    }
 }
 
 /// Front-matter of blog.
@@ -192,6 +695,10 @@ impl Render for BlogEntry {
 pub struct BlogMetadata {
     pub title: String,
     pub date: Option<jiff::civil::Date>,
+    #[serde(default)]
+    pub status: Option<PostStatus>,
+    /// Older way to mark drafts, use [`Self::status`] instead.
+    #[serde(default)]
     pub draft: Option<bool>,
     #[serde(default)]
     pub topics: Vec<String>,
@@ -199,6 +706,97 @@ pub struct BlogMetadata {
     pub lang: Language,
     #[serde(default)]
     pub numbered_headings: Option<bool>,
+    /// Whether to show a table of contents above the post.
+    #[serde(default)]
+    pub toc: bool,
+    /// Name of the series the post is part of.
+    #[serde(default)]
+    pub series: Option<String>,
+    /// Position of the post in its series.
+    #[serde(default)]
+    pub part: Option<NonZeroU32>,
+    /// KaTeX macros of the post, on top of the ones of the site config.
+    #[serde(default)]
+    pub math_macros: BTreeMap<String, String>,
+}
+
+impl FrontMatter for BlogMetadata {
+    const FIELDS: &'static [Field] = &[
+        Field::required::<String>("title", "a string"),
+        Field::optional::<jiff::civil::Date>("date", "a date like 2024-01-31"),
+        Field::optional::<PostStatus>(
+            "status",
+            "one of draft, unlisted, scheduled, published or archived",
+        ),
+        Field::optional::<bool>("draft", "true or false"),
+        Field::optional::<Vec<String>>("topics", "a list of topics"),
+        Field::optional::<Language>("lang", "English or Spanish"),
+        Field::optional::<bool>("numbered-headings", "true or false"),
+        Field::optional::<bool>("toc", "true or false"),
+        Field::optional::<String>("series", "the name of a series"),
+        Field::optional::<NonZeroU32>("part", "a positive whole number"),
+        Field::optional::<BTreeMap<String, String>>(
+            "math-macros",
+            "a mapping from macros to their definitions",
+        ),
+    ];
+}
+
+impl BlogMetadata {
+    /// The status set in the front matter.
+    ///
+    /// Posts are drafts unless explicitly marked otherwise, either with
+    /// `status` or with the older `draft: false`.
+    pub fn status(&self) -> eyre::Result<PostStatus> {
+        match (self.status, self.draft) {
+            (Some(_), Some(_)) => eyre::bail!("Only one of `status` and `draft` can be set"),
+            (Some(status), None) => Ok(status),
+            (None, Some(false)) => Ok(PostStatus::Published),
+            (None, _) => Ok(PostStatus::Draft),
+        }
+    }
+}
+
+/// Publication status of a blog post.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PostStatus {
+    /// Only built when including drafts.
+    Draft,
+    /// Built, but left out of the blog home, the feeds and the sitemap.
+    Unlisted,
+    /// Like a draft until the day of its `date`, published from then on.
+    Scheduled,
+    Published,
+    /// Published, with a notice saying that it might be outdated.
+    Archived,
+}
+
+impl PostStatus {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Draft => "draft",
+            Self::Unlisted => "unlisted",
+            Self::Scheduled => "scheduled",
+            Self::Published => "published",
+            Self::Archived => "archived",
+        }
+    }
+
+    /// Whether posts with this status are built in release builds.
+    pub const fn is_public(self) -> bool {
+        !matches!(self, Self::Draft | Self::Scheduled)
+    }
+
+    /// Class to style the summary of the post in the blog home.
+    const fn class(self) -> Option<&'static str> {
+        match self {
+            Self::Draft => Some("draft-post"),
+            Self::Scheduled => Some("scheduled-post"),
+            Self::Archived => Some("archived-post"),
+            Self::Unlisted | Self::Published => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
@@ -216,3 +814,82 @@ impl Language {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collection,
+        testing::{self, entry, post},
+    };
+
+    #[test]
+    fn scheduled_posts_are_parsed_again_when_published() {
+        let inputs = |content: &str, today| {
+            let mut config = testing::config();
+            config.build.today = today;
+            Hash::of(&BlogEntry::cache_inputs(content, &config))
+        };
+        let (before, on, after) = (
+            jiff::civil::date(2025, 1, 9),
+            jiff::civil::date(2025, 1, 10),
+            jiff::civil::date(2025, 1, 11),
+        );
+
+        let scheduled = "---\ntitle: Later\ndate: 2025-01-10\nstatus: scheduled\n---\n";
+        assert_ne!(inputs(scheduled, before), inputs(scheduled, on));
+        assert_eq!(inputs(scheduled, on), inputs(scheduled, after));
+
+        let published = post("Now", "date: 2025-01-10\n", "");
+        assert_eq!(inputs(&published, before), inputs(&published, after));
+    }
+
+    #[test]
+    fn drafts_are_only_previewed() {
+        let mut config = testing::dev_config();
+        config.build.drafts = true;
+        let entries = [
+            entry("published", &post("Published", "", ""), &config),
+            entry("draft", "---\ntitle: Draft\n---\n", &config),
+        ];
+        assert!(!entries[1].is_public());
+
+        let html = BlogEntry::render_index(&config, &entries).into_string();
+        assert!(html.contains("Draft"), "Drafts should be in the blog home");
+
+        let rss = (BlogEntry::EXTRA_OUTPUTS[0].render)(&config, &entries).unwrap();
+        assert!(rss.contains("Published") && !rss.contains("Draft"));
+
+        let neighbours = collection::neighbours(&entries);
+        assert!(
+            neighbours[0].next.is_none() && neighbours[1].previous.is_none(),
+            "Drafts shouldn't be linked from other posts"
+        );
+    }
+
+    #[test]
+    fn topic_slugs_are_unique() {
+        let config = testing::config();
+        let post = |slug, topic| {
+            let content = post("Post", &format!("topics: [{topic}]\n"), "");
+            entry(slug, &content, &config)
+        };
+        let entries = [post("first", "Rust"), post("second", "rust")];
+
+        let Err(err) = BlogEntry::derived_outputs(&config, &entries) else {
+            panic!("Topics with the same slug should be an error");
+        };
+        assert!(
+            err.to_string()
+                .contains("\"Rust\" and \"rust\" would have the same page at /blog/topics/rust/"),
+            "Unexpected error: {err}"
+        );
+    }
+
+    #[test]
+    fn topic_slugs_are_lowercase() {
+        assert_eq!(topic_slug("Rust & C++"), "rust---c--");
+        assert_eq!(topic_slug("Ελληνικά"), "ελληνικά");
+        assert_eq!(topic_slug("Ärger"), "ärger");
+    }
+}
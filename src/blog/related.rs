@@ -0,0 +1,99 @@
+//! Related posts, to have something to read next at the end of each post.
+//!
+//! Posts are related by the topics they share and by the similarity of their
+//! text: the cosine similarity of their TF-IDF vectors, so that words that
+//! appear in every post don't count much.
+
+use std::collections::BTreeMap;
+
+use crate::{blog::BlogEntry, search};
+
+/// Number of related posts of each post.
+const MAX_RELATED: usize = 3;
+
+/// How much each shared topic adds to the similarity, which is at most 1.
+const TOPIC_WEIGHT: f64 = 0.5;
+
+/// Posts less related than this aren't shown, even if there are no others.
+const MIN_SCORE: f64 = 0.05;
+
+/// The posts related to each of `entries`, as indices, most related first.
+pub fn related(entries: &[&BlogEntry]) -> Vec<Vec<usize>> {
+    let vectors = tf_idf(entries);
+
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let mut scores = entries
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(j, other)| {
+                    let shared_topics = entry
+                        .metadata
+                        .topics
+                        .iter()
+                        .filter(|topic| other.metadata.topics.contains(topic))
+                        .count();
+                    let score =
+                        shared_topics as f64 * TOPIC_WEIGHT + similarity(&vectors[i], &vectors[j]);
+                    (j, score)
+                })
+                .filter(|&(_, score)| score >= MIN_SCORE)
+                .collect::<Vec<_>>();
+
+            // Ties are broken by position, so that the result is deterministic.
+            scores.sort_by(|(a, a_score), (b, b_score)| b_score.total_cmp(a_score).then(a.cmp(b)));
+            scores.truncate(MAX_RELATED);
+            scores.into_iter().map(|(j, _)| j).collect()
+        })
+        .collect()
+}
+
+/// Normalized TF-IDF vector of the title and text of each of `entries`.
+fn tf_idf(entries: &[&BlogEntry]) -> Vec<BTreeMap<String, f64>> {
+    let frequencies = entries
+        .iter()
+        .map(|entry| {
+            let text = format!("{} {}", entry.metadata.title, search::text(&entry.html));
+            let mut frequencies = BTreeMap::<String, f64>::new();
+            for term in search::tokenize(&text) {
+                *frequencies.entry(term).or_default() += 1.0;
+            }
+            frequencies
+        })
+        .collect::<Vec<_>>();
+
+    let mut document_frequencies = BTreeMap::<&str, f64>::new();
+    for term in frequencies.iter().flat_map(BTreeMap::keys) {
+        *document_frequencies.entry(term).or_default() += 1.0;
+    }
+
+    let count = entries.len() as f64;
+    frequencies
+        .iter()
+        .map(|frequencies| {
+            let mut vector = frequencies
+                .iter()
+                .map(|(term, frequency)| {
+                    let idf = (count / document_frequencies[term.as_str()]).ln();
+                    (term.clone(), frequency * idf)
+                })
+                .collect::<BTreeMap<_, _>>();
+
+            let norm = vector.values().map(|x| x * x).sum::<f64>().sqrt();
+            if norm > 0.0 {
+                vector.values_mut().for_each(|x| *x /= norm);
+            }
+            vector
+        })
+        .collect()
+}
+
+/// Cosine similarity of two normalized vectors.
+fn similarity(a: &BTreeMap<String, f64>, b: &BTreeMap<String, f64>) -> f64 {
+    a.iter()
+        .filter_map(|(term, x)| Some(x * b.get(term)?))
+        .sum()
+}
@@ -0,0 +1,250 @@
+//! Series of posts, which are meant to be read in order.
+//!
+//! Posts with the same `series` in their front matter are grouped, ordered by
+//! their `part`. Posts without one go after the numbered ones, by date, and
+//! are numbered after the last of them. Each post shows every part of its
+//! series, and each series has a page at `/blog/series/<name>/`.
+
+use color_eyre::eyre;
+use maud::{Markup, html};
+use std::{collections::BTreeMap, num::NonZeroU32};
+
+use crate::{
+    blog::{BlogEntry, posts, topic_slug},
+    collection::{Collection, SeriesLinks},
+    components::back,
+};
+
+pub const DESC: &str = "posts that are meant to be read in order.";
+
+/// A series of posts.
+pub struct Series<'a> {
+    pub name: &'a str,
+    /// The posts of the series, in order.
+    pub parts: Vec<&'a BlogEntry>,
+}
+
+impl Series<'_> {
+    /// Directory of the page of the series, relative to the output directory.
+    pub fn path(&self) -> String {
+        format!("blog/series/{}", topic_slug(self.name))
+    }
+
+    /// Number of each part. Posts without one are numbered after the last
+    /// part with a number, so that they can't have the same one.
+    fn numbers(&self) -> impl Iterator<Item = u32> {
+        let last = self
+            .parts
+            .iter()
+            .filter_map(|entry| entry.metadata.part)
+            .max()
+            .map_or(0, NonZeroU32::get);
+
+        let mut next = last;
+        self.parts.iter().map(move |entry| {
+            entry.metadata.part.map_or_else(
+                || {
+                    next += 1;
+                    next
+                },
+                NonZeroU32::get,
+            )
+        })
+    }
+
+    /// Links to the parts of the series, for the post at `current`.
+    fn links(&self, current: usize) -> SeriesLinks {
+        SeriesLinks {
+            name: self.name.to_string(),
+            href: format!("/{}/", self.path()),
+            parts: self
+                .numbers()
+                .zip(&self.parts)
+                .map(|(number, entry)| (number, entry.link()))
+                .collect(),
+            current,
+        }
+    }
+}
+
+/// The series of `entries`, by name.
+///
+/// Posts with the same part as another should have been left out with
+/// [`duplicate_parts`].
+pub fn series<'a>(entries: impl IntoIterator<Item = &'a BlogEntry>) -> Vec<Series<'a>> {
+    let mut series = BTreeMap::<&str, Vec<&BlogEntry>>::new();
+    for entry in entries {
+        if let Some(name) = &entry.metadata.series {
+            series.entry(name).or_default().push(entry);
+        }
+    }
+
+    series
+        .into_iter()
+        .map(|(name, mut parts)| {
+            parts.sort_by(|a, b| {
+                let key = |entry: &BlogEntry| {
+                    let part = entry.metadata.part;
+                    (part.is_none(), part, entry.metadata.date)
+                };
+                key(a).cmp(&key(b)).then_with(|| a.slug.cmp(&b.slug))
+            });
+
+            Series { name, parts }
+        })
+        .collect()
+}
+
+/// The listed posts with the same part of their series as an earlier one (by
+/// date), as indices in `entries` with the error of each one.
+pub fn duplicate_parts(entries: &[BlogEntry]) -> Vec<(usize, eyre::Report)> {
+    let mut parts = BTreeMap::<(&str, NonZeroU32), Vec<usize>>::new();
+    for (i, entry) in entries.iter().enumerate() {
+        if let (Some(name), Some(part)) = (&entry.metadata.series, entry.metadata.part)
+            && entry.is_listed()
+        {
+            parts.entry((name, part)).or_default().push(i);
+        }
+    }
+
+    let mut duplicates = Vec::new();
+    for ((name, part), mut posts) in parts {
+        posts.sort_by_key(|&i| (entries[i].metadata.date, &entries[i].slug));
+        let first = &entries[posts[0]].slug;
+        for &i in &posts[1..] {
+            let error = eyre::eyre!(
+                "Part {part} of the series {name:?} is already the post {first:?}, \
+                 parts should be unique"
+            );
+            duplicates.push((i, error));
+        }
+    }
+
+    duplicates.sort_by_key(|&(i, _)| i);
+    duplicates
+}
+
+/// The [`SeriesLinks`] of each of `entries`, for [`Collection::series`].
+pub fn links(entries: &[&BlogEntry]) -> Vec<Option<SeriesLinks>> {
+    let mut links = BTreeMap::new();
+    for series in series(entries.iter().copied()) {
+        for (position, entry) in series.parts.iter().enumerate() {
+            links.insert(entry.slug.as_str(), series.links(position));
+        }
+    }
+
+    entries
+        .iter()
+        .map(|entry| links.remove(entry.slug.as_str()))
+        .collect()
+}
+
+/// Box with every part of the series, at the top of each of its posts.
+pub fn render_box(links: &SeriesLinks) -> Markup {
+    let (number, _) = &links.parts[links.current];
+
+    html! {
+        aside."series-box mb-6" {
+            ."faint text-sm mb-1" {
+                "part " (number) " of " (links.parts.len()) " of the series "
+                a href=(links.href) { (links.name) }
+            }
+            ol {
+                @for (i, (number, part)) in links.parts.iter().enumerate() {
+                    li {
+                        span."text-primary" { (number) ". " }
+                        @if i == links.current {
+                            span."font-bold" { (part.title) }
+                        } @else {
+                            a href=(part.href) { (part.title) }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Page with the posts of `series`, in order.
+pub fn page(series: &Series) -> Markup {
+    html! {
+        (back())
+
+        h1 { (series.name) }
+        p."pb-4 faint" { "a series of " (posts(series.parts.len())) ", meant to be read in order." }
+
+        ol {
+            @for entry in &series.parts {
+                li."mb-4" { (entry.render_summary()) }
+            }
+        }
+    }
+}
+
+/// Page with every series of the blog.
+pub fn overview(series: &[Series]) -> Markup {
+    html! {
+        (back())
+
+        h1 { "series" }
+        p."pb-4 faint" { (DESC) }
+
+        ul."flex flex-col gap-2" {
+            @for series in series {
+                li."text-lg" {
+                    a."text-secondary" href=(format!("/{}/", series.path())) { (series.name) }
+                    span."faint" { " (" (posts(series.parts.len())) ")" }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        collection,
+        testing::{self, entry, post},
+    };
+
+    #[test]
+    fn parts_are_unique() {
+        let config = testing::config();
+        let post = |slug: &str, date: &str, part: Option<u32>| {
+            let part = part.map_or(String::new(), |part| format!("part: {part}\n"));
+            let content = post(slug, &format!("date: {date}\nseries: Twice\n{part}"), "");
+            entry(slug, &content, &config)
+        };
+
+        // The later post with the same part is the one left out.
+        let entries = [
+            post("first", "2024-01-01", Some(2)),
+            post("second", "2024-02-01", Some(2)),
+        ];
+        let conflicts = BlogEntry::conflicts(&entries);
+        assert_eq!(conflicts.len(), 1, "Only the later post conflicts");
+        let (i, err) = &conflicts[0];
+        assert_eq!(entries[*i].slug, "second");
+        assert!(
+            err.to_string()
+                .contains("Part 2 of the series \"Twice\" is already the post \"first\""),
+            "Unexpected error: {err}"
+        );
+
+        // Posts without a part are numbered after the ones with a part, even if
+        // they're older.
+        let entries = [
+            post("unnumbered", "2024-01-01", None),
+            post("numbered", "2024-02-01", Some(1)),
+        ];
+        assert!(BlogEntry::conflicts(&entries).is_empty());
+        let series = collection::neighbours(&entries)[0].series.clone().unwrap();
+        let parts = series
+            .parts
+            .iter()
+            .map(|(number, link)| (*number, link.title.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(parts, [(1, "numbered"), (2, "unnumbered")]);
+    }
+}
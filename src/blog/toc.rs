@@ -0,0 +1,87 @@
+//! Table of contents of long posts, made from their headings.
+//!
+//! Only the two top levels of headings of each post are listed. With
+//! `numbered-headings`, they get the same numbers as in the post, which also
+//! numbers its two top levels (see [`top_level`]), whichever they are.
+
+use maud::{Markup, html};
+use serde::{Deserialize, Serialize};
+
+/// A heading of a post.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Heading {
+    /// Level of the heading in the page (the title of the post is the only `h1`).
+    pub level: u8,
+    /// Id of the heading, unique in the post.
+    pub id: String,
+    /// Text of the heading, without markup.
+    pub title: String,
+}
+
+/// Level of the shallowest of `headings`, which are numbered from it.
+pub fn top_level(headings: &[Heading]) -> u8 {
+    headings.iter().map(|entry| entry.level).min().unwrap_or(2)
+}
+
+/// Table of contents of `headings`, numbered like the headings of the post
+/// if `numbered`.
+pub fn render(headings: &[Heading], numbered: bool) -> Markup {
+    let top = top_level(headings);
+
+    // Same as the counters of `.numbered-headings` in the stylesheet.
+    let mut heading = 0;
+    let mut subheading = 0;
+
+    html! {
+        nav."toc mb-6" aria-label="Table of contents" {
+            ."faint text-sm mb-1" { "contents" }
+            ol {
+                @for entry in headings.iter().filter(|entry| entry.level <= top + 1) {
+                    @let number = if entry.level == top {
+                        heading += 1;
+                        subheading = 0;
+                        format!("{heading}) ")
+                    } else {
+                        subheading += 1;
+                        format!("{heading}.{subheading}) ")
+                    };
+
+                    li."pl-[2ch]"[entry.level > top] {
+                        a href=(format!("#{}", entry.id)) {
+                            @if numbered {
+                                span."text-primary" { (number) }
+                            }
+                            (entry.title)
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_start_at_the_top_level() {
+        let heading = |level, title: &str| Heading {
+            level,
+            id: title.to_string(),
+            title: title.to_string(),
+        };
+        let headings = [
+            heading(3, "a"),
+            heading(4, "b"),
+            heading(5, "c"),
+            heading(3, "d"),
+        ];
+
+        let html = render(&headings, true).into_string();
+        for number in ["1) </span>a", "1.1) </span>b", "2) </span>d"] {
+            assert!(html.contains(number), "Missing {number:?} in {html}");
+        }
+        assert!(!html.contains(">c<"), "Only two levels should be listed");
+    }
+}
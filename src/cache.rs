@@ -0,0 +1,223 @@
+//! Build cache, to make incremental builds fast.
+//!
+//! For every output file we record a hash of the inputs used to generate it
+//! (markdown source, front-matter, other entries...). If the inputs of a page
+//! haven't changed since the last build, and the file is still there, it is
+//! neither rendered nor written again.
+//!
+//! Parsed entries are also cached (keyed by the hash of their source), so that
+//! unchanged markdown files don't need to be parsed again just to build the
+//! index pages and feeds.
+//!
+//! The whole cache is discarded when the [`TEMPLATE_VERSION`], the crate
+//! version or the site configuration (including the build options, except for
+//! the date) change.
+
+use color_eyre::eyre::{self, WrapErr as _};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::config::SiteConfig;
+
+/// Version of the templates, which is a hash of the sources and assets of the
+/// site made by `build.rs`.
+///
+/// Any change to them (even one that doesn't change the output) discards the
+/// cache, so that stale pages never survive it.
+pub const TEMPLATE_VERSION: &str = env!("TEMPLATE_VERSION");
+
+/// Name of the cache file, inside the cache directory of the site.
+const CACHE_FILE: &str = ".build-cache.json";
+
+/// Hash of the inputs of some output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Hash(#[serde(with = "hex")] blake3::Hash);
+
+impl Hash {
+    /// Hashes the serialized representation of `value`.
+    pub fn of(value: &impl Serialize) -> Self {
+        let mut hasher = blake3::Hasher::new();
+        serde_json::to_writer(&mut hasher, value).expect("Inputs should be serializable");
+        Self(hasher.finalize())
+    }
+}
+
+/// The build cache.
+///
+/// It can be shared between threads, since sections and entries are
+/// generated in parallel.
+#[derive(Debug)]
+pub struct BuildCache {
+    output: PathBuf,
+    /// Path of the cache file.
+    path: PathBuf,
+    state: Mutex<CacheState>,
+}
+
+/// Contents of the cache file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheState {
+    /// Hash of everything that affects all the outputs at once.
+    fingerprint: Option<Hash>,
+    /// Hash of the inputs of each output file, relative to the output directory.
+    pages: BTreeMap<PathBuf, Hash>,
+    /// Parsed entries, keyed by section and slug.
+    entries: BTreeMap<String, CachedEntry>,
+
+    /// Keys used in this build, to prune the rest when saving.
+    #[serde(skip)]
+    used_pages: BTreeSet<PathBuf>,
+    #[serde(skip)]
+    used_entries: BTreeSet<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedEntry {
+    inputs: Hash,
+    data: serde_json::Value,
+}
+
+impl BuildCache {
+    /// Loads the cache in the cache directory of `config`.
+    ///
+    /// If there is no cache, it is unreadable or it was made for a different
+    /// configuration, an empty cache is returned.
+    pub fn load(config: &SiteConfig) -> Self {
+        let fingerprint = Hash::of(&(TEMPLATE_VERSION, env!("CARGO_PKG_VERSION"), config));
+
+        let path = config.cache_dir.join(CACHE_FILE);
+        let state = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<CacheState>(&content).ok())
+            .filter(|state| state.fingerprint == Some(fingerprint));
+
+        let mut state = match state {
+            Some(state) => {
+                tracing::info!(pages = state.pages.len(), "Loaded build cache");
+                state
+            }
+            None => {
+                tracing::info!("No valid build cache found, doing a full build");
+                CacheState::default()
+            }
+        };
+
+        state.fingerprint = Some(fingerprint);
+        Self {
+            output: config.output.clone(),
+            path,
+            state: Mutex::new(state),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, CacheState> {
+        self.state
+            .lock()
+            .expect("Build cache lock shouldn't be poisoned")
+    }
+
+    /// Writes the cache to disk.
+    ///
+    /// If `prune` is set, the outputs and entries that were not used in this
+    /// build are forgotten.
+    pub fn save(self, prune: bool) -> eyre::Result<()> {
+        let mut state = self
+            .state
+            .into_inner()
+            .expect("Build cache lock shouldn't be poisoned");
+        let CacheState {
+            pages,
+            entries,
+            used_pages,
+            used_entries,
+            ..
+        } = &mut state;
+        if prune {
+            pages.retain(|page, _| used_pages.contains(page));
+            entries.retain(|key, _| used_entries.contains(key));
+        }
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_string(&state).wrap_err("Couldn't serialize build cache")?;
+        fs::write(&self.path, content)
+            .wrap_err_with(|| format!("Couldn't write build cache at {:?}", self.path))
+    }
+
+    /// Whether `page` was generated from the same `inputs` and is still on disk.
+    ///
+    /// Marks the page as used in this build either way.
+    pub fn is_fresh(&self, page: &Path, inputs: Hash) -> bool {
+        let mut state = self.state();
+        state.used_pages.insert(page.to_path_buf());
+        state.pages.get(page) == Some(&inputs) && self.output.join(page).exists()
+    }
+
+    /// Records that `page` was generated from `inputs`.
+    pub fn record(&self, page: &Path, inputs: Hash) {
+        let mut state = self.state();
+        state.used_pages.insert(page.to_path_buf());
+        state.pages.insert(page.to_path_buf(), inputs);
+    }
+
+    /// Gets the entry with `key` if it was parsed from the same `inputs`,
+    /// otherwise parses it with `parse` and caches the result.
+    pub fn entry<T: Serialize + DeserializeOwned>(
+        &self,
+        key: &str,
+        inputs: Hash,
+        parse: impl FnOnce() -> eyre::Result<T>,
+    ) -> eyre::Result<T> {
+        let cached = {
+            let mut state = self.state();
+            state.used_entries.insert(key.to_string());
+            state
+                .entries
+                .get(key)
+                .filter(|cached| cached.inputs == inputs)
+                .map(|cached| cached.data.clone())
+        };
+
+        if let Some(cached) = cached {
+            match serde_json::from_value(cached) {
+                Ok(data) => {
+                    tracing::debug!(?key, "Using cached entry");
+                    return Ok(data);
+                }
+                Err(err) => tracing::warn!(?key, ?err, "Couldn't read cached entry"),
+            }
+        }
+
+        // Not holding the lock here, so that entries get parsed in parallel.
+        let data = parse()?;
+        let cached = CachedEntry {
+            inputs,
+            data: serde_json::to_value(&data).wrap_err("Couldn't serialize entry for cache")?,
+        };
+        self.state().entries.insert(key.to_string(), cached);
+
+        Ok(data)
+    }
+}
+
+mod hex {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error as _};
+
+    pub fn serialize<S: Serializer>(hash: &blake3::Hash, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hash.to_hex())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<blake3::Hash, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        blake3::Hash::from_hex(hex).map_err(D::Error::custom)
+    }
+}
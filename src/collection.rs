@@ -0,0 +1,255 @@
+//! Sections of the site made of a directory of markdown entries, such as the
+//! blog and the media log.
+//!
+//! Each entry gets its own page at `/<PATH>/<slug>/`, and there is an index
+//! with all of them at `/<PATH>/`. The generator drives the rest (reading the
+//! directory, caching, writing the pages...), so adding a new section like
+//! this is just an implementation of [`Collection`].
+
+use color_eyre::eyre;
+use jiff::civil::Date;
+use maud::Markup;
+use serde::{Serialize, de::DeserializeOwned};
+use std::path::Path;
+
+use crate::{PageMeta, cache::Hash, config::SiteConfig, search};
+
+/// A file generated from all the entries of a collection, other than the
+/// index (e.g. feeds).
+pub struct ExtraOutput<T> {
+    /// Path relative to the output directory.
+    pub path: &'static str,
+    pub render: fn(&SiteConfig, &[T]) -> eyre::Result<String>,
+}
+
+/// A file generated for each entry of a collection, next to its page (e.g.
+/// preview images).
+pub struct EntryOutput<T> {
+    /// Name of the file, in the directory of the page of the entry.
+    pub file_name: &'static str,
+    /// Hash of what the file depends on, so that it's only generated again
+    /// when that changes.
+    pub inputs: fn(&T) -> Hash,
+    pub render: fn(&T) -> eyre::Result<Vec<u8>>,
+}
+
+/// A file generated from some of the entries of a collection, whose path
+/// depends on them (e.g. the page of each topic).
+///
+/// These are rendered in every build, but only written when they change.
+pub enum DerivedOutput {
+    /// A page, which gets the shell of the site and goes in the sitemap.
+    Page {
+        /// Path relative to the output directory.
+        path: String,
+        meta: PageMeta,
+        content: Markup,
+    },
+    File {
+        /// Path relative to the output directory.
+        path: String,
+        content: String,
+    },
+}
+
+/// Link to the page of an entry, from the page of another one.
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryLink {
+    /// Path of the page, from the root of the site.
+    pub href: String,
+    pub title: String,
+    pub date: Option<Date>,
+}
+
+/// Links from the page of an entry to the other entries of the collection.
+///
+/// They're part of the inputs of the page, so that it's generated again when
+/// they change.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Neighbours {
+    /// The previous entry in the index (i.e. the newer one, for dated entries).
+    pub previous: Option<EntryLink>,
+    /// The next entry in the index (i.e. the older one, for dated entries).
+    pub next: Option<EntryLink>,
+    /// The most related entries, most related first.
+    pub related: Vec<EntryLink>,
+    /// The series the entry is part of, if any.
+    pub series: Option<SeriesLinks>,
+}
+
+/// Links to every part of a series of entries, which are read in order.
+#[derive(Debug, Clone, Serialize)]
+pub struct SeriesLinks {
+    pub name: String,
+    /// Path of the page of the series, from the root of the site.
+    pub href: String,
+    /// Every part of the series, in order, with the number of each one.
+    pub parts: Vec<(u32, EntryLink)>,
+    /// Position of the entry in [`Self::parts`].
+    pub current: usize,
+}
+
+/// The [`Neighbours`] of each of the `entries`, which should be sorted.
+///
+/// Only listed and public entries link to each other, the rest get no
+/// neighbours.
+pub fn neighbours<T: Collection>(entries: &[T]) -> Vec<Neighbours> {
+    let listed = entries
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| entry.is_listed() && entry.is_public())
+        .collect::<Vec<_>>();
+    let listed_entries = listed.iter().map(|&(_, entry)| entry).collect::<Vec<_>>();
+    let related = T::related(&listed_entries);
+    let mut series = T::series(&listed_entries);
+
+    let mut neighbours = vec![Neighbours::default(); entries.len()];
+    for (position, &(i, _)) in listed.iter().enumerate() {
+        let link = |position: usize| listed.get(position).map(|(_, entry)| entry.link());
+        neighbours[i] = Neighbours {
+            previous: position.checked_sub(1).and_then(link),
+            next: link(position + 1),
+            related: related
+                .get(position)
+                .into_iter()
+                .flatten()
+                .map(|&other| listed[other].1.link())
+                .collect(),
+            series: series.get_mut(position).and_then(Option::take),
+        };
+    }
+
+    neighbours
+}
+
+/// An entry of a collection.
+///
+/// Entries are serializable so that they can be kept in the build cache, so
+/// everything needed to render them must be serialized.
+pub trait Collection: Serialize + DeserializeOwned + Send + Sync + 'static {
+    /// Name of an entry, for logs.
+    const NAME: &'static str;
+    /// Path of the collection in the site, without slashes.
+    const PATH: &'static str;
+    /// Files generated from all the entries, besides the index.
+    const EXTRA_OUTPUTS: &'static [ExtraOutput<Self>] = &[];
+    /// Files generated for each entry, besides its page.
+    const ENTRY_OUTPUTS: &'static [EntryOutput<Self>] = &[];
+
+    /// Directory with the markdown files of the entries.
+    fn source_dir(config: &SiteConfig) -> &Path;
+
+    /// Parses the entry with `slug` from its markdown file.
+    ///
+    /// Returns `None` for entries that shouldn't be published in this build,
+    /// such as drafts.
+    fn parse(slug: &str, content: &str, config: &SiteConfig) -> eyre::Result<Option<Self>>;
+
+    /// What [`Self::parse`] depends on besides the file and the configuration,
+    /// so that the cached entry is parsed again when it changes.
+    ///
+    /// The date of the build isn't part of the fingerprint of the cache, so
+    /// entries that depend on it should return what they use of it here.
+    fn cache_inputs(_content: &str, _config: &SiteConfig) -> impl Serialize {}
+
+    /// Validates the markdown file of the entry with `slug`, for the `check`
+    /// command, returning its [warnings](Self::warnings). Unlike
+    /// [`Self::parse`], this shouldn't access the network.
+    fn check(slug: &str, content: &str, config: &SiteConfig) -> eyre::Result<Vec<String>> {
+        let entry = Self::parse(slug, content, config)?;
+        Ok(entry
+            .map(|entry| entry.warnings().to_vec())
+            .unwrap_or_default())
+    }
+
+    fn slug(&self) -> &str;
+
+    /// Link to the page of the entry.
+    fn link(&self) -> EntryLink;
+
+    /// When the entry was last modified, for the sitemap.
+    fn date(&self) -> Option<Date>;
+
+    /// Entries are listed in increasing order of this key.
+    fn sort_key(&self) -> impl Ord;
+
+    /// Files referenced by the entry, relative to the source directory, which
+    /// get copied to the same place relative to [`Self::PATH`].
+    fn assets(&self) -> &[String] {
+        &[]
+    }
+
+    /// Whether the entry is shown in the index, the extra outputs and the
+    /// sitemap. Unlisted entries still get their page.
+    fn is_listed(&self) -> bool {
+        true
+    }
+
+    /// Whether the entry is in the feeds, the sitemap, the search and the
+    /// related entries. Listed entries that aren't public (like drafts, when
+    /// they're built) are only previewed in the index.
+    fn is_public(&self) -> bool {
+        true
+    }
+
+    /// Problems of the entry that don't stop it from being built, which are
+    /// logged in every build (even if the entry comes from the cache).
+    fn warnings(&self) -> &[String] {
+        &[]
+    }
+
+    /// Whether the entry is missing remote data because of an offline build.
+    fn is_stale(&self) -> bool {
+        false
+    }
+
+    /// What the search finds of the entry, if it should be searchable.
+    fn search_document(&self) -> Option<search::Document> {
+        None
+    }
+
+    /// Metadata of the page of the entry.
+    fn meta(&self) -> PageMeta;
+
+    /// Contents of the page of the entry.
+    fn render_page(&self, neighbours: &Neighbours) -> Markup;
+
+    /// The entries related to each of the listed `entries`, as indices in
+    /// `entries`, most related first.
+    ///
+    /// By default, there are no related entries.
+    fn related(_entries: &[&Self]) -> Vec<Vec<usize>> {
+        Vec::new()
+    }
+
+    /// The series each of the listed `entries` is part of, if any.
+    ///
+    /// By default, entries are not part of any series.
+    fn series(_entries: &[&Self]) -> Vec<Option<SeriesLinks>> {
+        Vec::new()
+    }
+
+    /// Entries that are invalid because of other entries (e.g. two parts of a
+    /// series with the same number), as indices in the sorted `entries` with
+    /// the error of each one.
+    ///
+    /// They are left out of the build, like the ones that fail to parse.
+    fn conflicts(_entries: &[Self]) -> Vec<(usize, eyre::Report)> {
+        Vec::new()
+    }
+
+    /// Metadata of the index page.
+    fn index_meta() -> PageMeta;
+
+    /// Contents of the index page, with all the `entries` already sorted.
+    fn render_index(config: &SiteConfig, entries: &[Self]) -> Markup;
+
+    /// Outputs that depend on what the `entries` are, besides the index and
+    /// the [`Self::EXTRA_OUTPUTS`].
+    fn derived_outputs(
+        _config: &SiteConfig,
+        _entries: &[Self],
+    ) -> eyre::Result<Vec<DerivedOutput>> {
+        Ok(Vec::new())
+    }
+}
@@ -0,0 +1,25 @@
+(async function () {
+  const POLL_INTERVAL = 500;
+  let version = null;
+
+  while (true) {
+    try {
+      const response = await fetch("{{LIVE_RELOAD_PATH}}", { cache: "no-store" });
+      if (!response.ok) {
+        // Not being served by the generator, so there's nothing to reload.
+        return;
+      }
+
+      const current = await response.text();
+      if (version !== null && current !== version) {
+        location.reload();
+        return;
+      }
+      version = current;
+    } catch {
+      // The server is probably restarting, keep trying.
+    }
+
+    await new Promise((resolve) => setTimeout(resolve, POLL_INTERVAL));
+  }
+})();
@@ -1,9 +1,14 @@
 use maud::{Markup, PreEscaped, html};
 
 pub fn back() -> Markup {
+    back_to("..")
+}
+
+/// Like [`back`], for pages where the parent directory is not a page.
+pub fn back_to(href: &str) -> Markup {
     html! {
         ."faint sticky absolute top-0 left-0 right-0 z-10 bg-neutral" {
-            a href=".." { "<-- (back)" }
+            a href=(href) { "<-- (back)" }
         }
     }
 }
@@ -42,3 +47,17 @@ pub fn theme_script() -> Markup {
         }
     }
 }
+
+/// Script that reloads the page after the site is rebuilt by [`crate::serve`].
+///
+/// It stops polling if the site is not being served by it.
+pub fn live_reload_script() -> Markup {
+    let script = include_str!("./live_reload.js")
+        .replace("{{LIVE_RELOAD_PATH}}", crate::serve::LIVE_RELOAD_PATH);
+
+    html! {
+        script {
+            (PreEscaped(script))
+        }
+    }
+}
@@ -0,0 +1,454 @@
+//! Site configuration, read from `site.toml`.
+//!
+//! Everything that is specific to a deployment of the site (base URL, author,
+//! where the content lives, where the output goes...) is declared here, so
+//! that staging copies or forks don't need to patch the source.
+//!
+//! String values that start with `$` are read from the environment variable of
+//! that name instead (e.g. `path = "$ODILF_BLOG_PATH"`). This is useful for
+//! machine-specific paths and for secrets such as the Immich API key.
+//!
+//! Relative paths are resolved from the directory that contains `site.toml`.
+
+use color_eyre::eyre::{self, WrapErr as _};
+use jiff::civil::Date;
+use serde::{Deserialize, Deserializer, Serialize, de::Error as _};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use url::Url;
+
+/// Default location of the site configuration, relative to the working directory.
+pub const DEFAULT_PATH: &str = "site.toml";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SiteConfig {
+    /// Public URL where the site is deployed, e.g. `https://odilf.com`.
+    ///
+    /// It can't have a path, since links between pages start from the root of
+    /// the domain.
+    #[serde(deserialize_with = "env_url")]
+    pub base_url: Url,
+    /// Title of the site, used for the `<title>` of the pages.
+    pub title: String,
+    pub author: Author,
+    /// Directory where the site gets generated.
+    ///
+    /// Defaults to `target/debug/site`, or `target/release/site` for release
+    /// builds (see [`BuildOptions::release`]).
+    #[serde(default, deserialize_with = "env_path")]
+    pub output: PathBuf,
+    /// Directory for what the build keeps between runs, such as the build
+    /// cache. It's outside [`Self::output`] so that it isn't deployed.
+    ///
+    /// Defaults to `target/debug/cache`, or `target/release/cache` for release
+    /// builds.
+    #[serde(default, deserialize_with = "env_path")]
+    pub cache_dir: PathBuf,
+    pub blog: BlogConfig,
+    pub media_log: MediaLogConfig,
+    #[serde(default)]
+    pub projects: ProjectsConfig,
+    #[serde(default)]
+    pub pics: PicsConfig,
+
+    /// Options of this particular build, which are set from the command line
+    /// instead of `site.toml`.
+    #[serde(skip_deserializing)]
+    pub build: BuildOptions,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct BuildOptions {
+    /// Build for deployment, fetching fresh remote data.
+    pub release: bool,
+    /// Include draft posts.
+    pub drafts: bool,
+    /// Don't access the network, using only cached remote data.
+    pub offline: bool,
+    /// Inject the live reload script of [`crate::serve`] in every page.
+    pub live_reload: bool,
+    /// Date of the build, to publish scheduled posts once it's past their date.
+    ///
+    /// It's left out of the fingerprint of the build cache, which would
+    /// otherwise be discarded every day. Instead, scheduled posts are parsed
+    /// again when they're published (see [`Collection::cache_inputs`]).
+    ///
+    /// [`Collection::cache_inputs`]: crate::collection::Collection::cache_inputs
+    #[serde(skip_serializing)]
+    pub today: Date,
+}
+
+impl BuildOptions {
+    /// Options from the flags of the command line, where `serve` is whether
+    /// the site is built for the development server.
+    ///
+    /// Drafts are only included with `drafts`, or when serving a development
+    /// build, which is also the only one with live reload. Serving a release
+    /// build previews the site as it's deployed.
+    pub fn from_flags(
+        release: bool,
+        drafts: bool,
+        offline: bool,
+        serve: bool,
+        today: Date,
+    ) -> Self {
+        let development_server = serve && !release;
+        Self {
+            release,
+            drafts: drafts || development_server,
+            offline,
+            live_reload: development_server,
+            today,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Author {
+    pub name: String,
+    pub email: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct BlogConfig {
+    /// Directory with the markdown files of the blog posts.
+    #[serde(deserialize_with = "env_path")]
+    pub path: PathBuf,
+    /// Number of posts in each page of the blog home.
+    #[serde(default = "default_posts_per_page")]
+    pub posts_per_page: usize,
+    pub feed: FeedConfig,
+    /// KaTeX macros available in every post, like `'\R' = '\mathbb{R}'`.
+    ///
+    /// Posts can add their own, or override these, with `math-macros` in their
+    /// front matter.
+    #[serde(default)]
+    pub math_macros: BTreeMap<String, String>,
+}
+
+/// Metadata of the RSS and Atom feeds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct FeedConfig {
+    pub title: String,
+    pub description: String,
+    #[serde(default = "default_language")]
+    pub language: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct MediaLogConfig {
+    /// Directory with the markdown files of the media log.
+    #[serde(deserialize_with = "env_path")]
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ProjectsConfig {
+    /// The `projects.toml` file with the (pre-fetched) projects.
+    #[serde(deserialize_with = "env_path")]
+    pub path: PathBuf,
+}
+
+impl Default for ProjectsConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("projects.toml"),
+        }
+    }
+}
+
+/// The Immich album with the pics.
+///
+/// Everything is optional (also when read from an unset environment variable),
+/// since it's only needed to fetch the album. Offline builds, the `check`
+/// command and builds of other sections work without it.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PicsConfig {
+    /// URL of the Immich server, e.g. `https://immich.example.com`.
+    #[serde(default, deserialize_with = "optional_env_url")]
+    pub immich_url: Option<Url>,
+    #[serde(default, deserialize_with = "optional_env_string")]
+    pub album_id: Option<String>,
+    #[serde(default, deserialize_with = "optional_env_string")]
+    pub api_key: Option<String>,
+}
+
+impl PicsConfig {
+    /// The URL of the server, the album id and the API key, which are needed
+    /// to fetch the album.
+    pub fn immich(&self) -> eyre::Result<(&Url, &str, &str)> {
+        match (&self.immich_url, &self.album_id, &self.api_key) {
+            (Some(url), Some(album_id), Some(api_key)) => Ok((url, album_id, api_key)),
+            _ => {
+                let missing = [
+                    ("pics.immich-url", self.immich_url.is_none()),
+                    ("pics.album-id", self.album_id.is_none()),
+                    ("pics.api-key", self.api_key.is_none()),
+                ]
+                .into_iter()
+                .filter(|&(_, missing)| missing)
+                .map(|(field, _)| format!("`{field}`"))
+                .collect::<Vec<_>>();
+                eyre::bail!(
+                    "Fetching the pics needs {} in the site configuration (or build offline)",
+                    missing.join(", ")
+                )
+            }
+        }
+    }
+}
+
+// Manual impl so that the API key doesn't end up in the logs.
+impl std::fmt::Debug for PicsConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PicsConfig")
+            .field("immich_url", &self.immich_url)
+            .field("album_id", &self.album_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SiteConfig {
+    /// Reads, resolves and validates the configuration at `path`, for a build
+    /// with the given options.
+    pub fn load(path: impl AsRef<Path>, build: BuildOptions) -> eyre::Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .wrap_err_with(|| format!("Couldn't read site configuration at {path:?}"))?;
+
+        let mut config: Self = toml::from_str(&content)
+            .wrap_err_with(|| format!("Invalid site configuration at {path:?}"))?;
+
+        config.build = build;
+        let profile = if build.release { "release" } else { "debug" };
+        if config.output.as_os_str().is_empty() {
+            config.output = Path::new("target").join(profile).join("site");
+        }
+        if config.cache_dir.as_os_str().is_empty() {
+            config.cache_dir = Path::new("target").join(profile).join("cache");
+        }
+
+        let root = path.parent().unwrap_or(Path::new("."));
+        config.resolve_paths(root);
+        config
+            .validate()
+            .wrap_err_with(|| format!("Invalid site configuration at {path:?}"))?;
+
+        Ok(config)
+    }
+
+    fn resolve_paths(&mut self, root: &Path) {
+        for path in [
+            &mut self.output,
+            &mut self.cache_dir,
+            &mut self.blog.path,
+            &mut self.media_log.path,
+            &mut self.projects.path,
+        ] {
+            if path.is_relative() {
+                *path = root.join(&*path);
+            }
+        }
+    }
+
+    /// Checks the invariants that can't be expressed by the types, reporting
+    /// all the problems at once.
+    fn validate(&self) -> eyre::Result<()> {
+        let mut errors = Vec::new();
+
+        for (field, url) in [
+            ("base-url", Some(&self.base_url)),
+            ("pics.immich-url", self.pics.immich_url.as_ref()),
+        ] {
+            let Some(url) = url else {
+                continue;
+            };
+            if !matches!(url.scheme(), "http" | "https") {
+                errors.push(format!("`{field}` must be an http(s) URL, got {url}"));
+            }
+        }
+        if self.base_url.query().is_some() || self.base_url.fragment().is_some() {
+            errors.push(format!(
+                "`base-url` can't have a query or fragment, got {}",
+                self.base_url
+            ));
+        }
+        if self.base_url.path() != "/" {
+            errors.push(format!(
+                "`base-url` can't have a path, since the links of the site start from the \
+                 root of the domain, got {}",
+                self.base_url
+            ));
+        }
+
+        for (field, value) in [
+            ("title", &self.title),
+            ("author.name", &self.author.name),
+            ("blog.feed.title", &self.blog.feed.title),
+        ] {
+            if value.trim().is_empty() {
+                errors.push(format!("`{field}` can't be empty"));
+            }
+        }
+
+        if !self.author.email.contains('@') {
+            errors.push(format!(
+                "`author.email` doesn't look like an email address: {:?}",
+                self.author.email
+            ));
+        }
+
+        for (field, path) in [
+            ("blog.path", &self.blog.path),
+            ("media-log.path", &self.media_log.path),
+        ] {
+            if !path.is_dir() {
+                errors.push(format!("`{field}` is not a directory: {path:?}"));
+            }
+        }
+        if !self.projects.path.is_file() {
+            errors.push(format!(
+                "`projects.path` is not a file: {:?}",
+                self.projects.path
+            ));
+        }
+
+        if errors.is_empty() {
+            return Ok(());
+        }
+
+        eyre::bail!("{}", errors.join("\n"))
+    }
+
+    /// The absolute URL of `path` in the deployed site.
+    pub fn url(&self, path: &str) -> Url {
+        self.base_url
+            .join(path.trim_start_matches('/'))
+            .expect("Base URL is validated to be an http(s) URL")
+    }
+
+    /// The absolute URL of the page at `file` (relative to the output
+    /// directory), without the `index.html`.
+    pub fn page_url(&self, file: &Path) -> Url {
+        let file = file.to_string_lossy();
+        self.url(file.strip_suffix("index.html").unwrap_or(&file))
+    }
+}
+
+fn default_language() -> String {
+    "en".to_string()
+}
+
+fn default_posts_per_page() -> usize {
+    20
+}
+
+/// Deserializes a string, reading it from the environment if it starts with `$`.
+fn env_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    let Some(var) = value.strip_prefix('$') else {
+        return Ok(value);
+    };
+
+    std::env::var(var)
+        .map_err(|_| D::Error::custom(format!("environment variable `{var}` is not set")))
+}
+
+fn env_path<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+    env_string(deserializer).map(PathBuf::from)
+}
+
+fn env_url<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Url, D::Error> {
+    let value = env_string(deserializer)?;
+    Url::parse(&value).map_err(|err| D::Error::custom(format!("invalid URL {value:?}: {err}")))
+}
+
+/// Deserializes a string like [`env_string`], but it's `None` if the variable
+/// isn't set or the string is empty.
+fn optional_env_string<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    let value = match value.strip_prefix('$') {
+        Some(var) => std::env::var(var).unwrap_or_default(),
+        None => value,
+    };
+
+    Ok(Some(value).filter(|value| !value.trim().is_empty()))
+}
+
+fn optional_env_url<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Url>, D::Error> {
+    optional_env_string(deserializer)?
+        .map(|value| {
+            Url::parse(&value)
+                .map_err(|err| D::Error::custom(format!("invalid URL {value:?}: {err}")))
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn build_options_from_flags() {
+        let today = jiff::civil::date(2025, 1, 1);
+        // (release, drafts, serve) => (drafts, live reload)
+        for (release, drafts, serve, expected) in [
+            (false, false, false, (false, false)),
+            (false, true, false, (true, false)),
+            (true, false, false, (false, false)),
+            (true, true, false, (true, false)),
+            (false, false, true, (true, true)),
+            (false, true, true, (true, true)),
+            (true, false, true, (false, false)),
+            (true, true, true, (true, false)),
+        ] {
+            let options = BuildOptions::from_flags(release, drafts, false, serve, today);
+            assert_eq!(
+                (options.drafts, options.live_reload),
+                expected,
+                "release: {release}, drafts: {drafts}, serve: {serve}"
+            );
+        }
+    }
+
+    #[test]
+    fn base_url_cant_have_a_path() {
+        let mut config = testing::config();
+        config.base_url = Url::parse("https://example.com/site/").unwrap();
+
+        let err = config.validate().unwrap_err().to_string();
+        assert!(
+            err.contains("`base-url` can't have a path"),
+            "Unexpected error: {err}"
+        );
+    }
+
+    #[test]
+    fn pics_are_only_needed_to_fetch_them() {
+        let pics = toml::from_str::<PicsConfig>(
+            "immich-url = \"$ODILF_SITE_UNSET_VARIABLE\"\nalbum-id = \"album\"\n",
+        )
+        .expect("Unset variables are allowed");
+        assert_eq!(pics.immich_url, None);
+        assert_eq!(pics.album_id.as_deref(), Some("album"));
+
+        let err = pics.immich().unwrap_err().to_string();
+        assert!(
+            err.contains("`pics.immich-url`, `pics.api-key`"),
+            "Unexpected error: {err}"
+        );
+    }
+}
@@ -0,0 +1,306 @@
+//! Parsing and validation of the YAML front matter of markdown files.
+//!
+//! Front matter is checked field by field against a schema (see
+//! [`FrontMatter::FIELDS`]) before deserializing it, so that errors point to
+//! the line and column of the offending field and say what was expected
+//! instead of just failing somewhere in the YAML. Unknown fields are allowed,
+//! but there is a warning for each one since they are usually typos. The
+//! warnings are returned with the front matter, so that they're kept with the
+//! entry (and shown again when it comes from the build cache).
+
+use color_eyre::eyre;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::{collections::BTreeMap, fmt};
+use yaml_rust2::{
+    Yaml, YamlLoader,
+    parser::{Event, MarkedEventReceiver, Parser},
+    scanner::Marker,
+};
+
+use crate::report::LineError;
+
+/// Line that opens and closes the front matter.
+const DELIMITER: &str = "---";
+
+/// Front matter that can be validated with a schema.
+pub trait FrontMatter: DeserializeOwned {
+    /// Fields of the front matter, with their names as written in YAML.
+    const FIELDS: &'static [Field];
+}
+
+/// A field of a [`FrontMatter`] schema.
+pub struct Field {
+    pub name: &'static str,
+    /// Description of the valid values, for error messages.
+    pub expected: &'static str,
+    pub required: bool,
+    check: fn(Value) -> Result<(), serde_json::Error>,
+}
+
+impl Field {
+    /// A field that must be present, with values of type `T`.
+    pub const fn required<T: DeserializeOwned>(name: &'static str, expected: &'static str) -> Self {
+        Self {
+            name,
+            expected,
+            required: true,
+            check: check::<T>,
+        }
+    }
+
+    /// A field that can be left out, with values of type `T`.
+    pub const fn optional<T: DeserializeOwned>(name: &'static str, expected: &'static str) -> Self {
+        Self {
+            name,
+            expected,
+            required: false,
+            check: check::<T>,
+        }
+    }
+}
+
+fn check<T: DeserializeOwned>(value: Value) -> Result<(), serde_json::Error> {
+    serde_json::from_value::<T>(value).map(drop)
+}
+
+/// Position in a file, both starting at 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Location {
+    line: usize,
+    column: usize,
+}
+
+impl Location {
+    /// Location in the file of `marker`, which is relative to the front matter.
+    fn from_marker(marker: Marker) -> Self {
+        Self {
+            // The front matter starts after the delimiter line.
+            line: marker.line() + 1,
+            column: marker.col() + 1,
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {} column {}", self.line, self.column)
+    }
+}
+
+/// Parses and validates the front matter at the start of `content`, returning
+/// it with a warning for each unknown field.
+///
+/// Errors point to the first invalid field, but list all of them.
+pub fn parse<T: FrontMatter>(content: &str) -> eyre::Result<(T, Vec<String>)> {
+    let yaml = split(content)
+        .ok_or_else(|| eyre::eyre!("Front matter not found, it should start with `{DELIMITER}`"))?;
+
+    let syntax_error = |error: yaml_rust2::ScanError| {
+        let location = Location::from_marker(*error.marker());
+        eyre::Report::new(LineError {
+            line: location.line,
+            column: Some(location.column),
+            message: format!("{} at {location}", error.info()),
+        })
+        .wrap_err("Invalid front matter")
+    };
+
+    let mut locations = FieldLocations::default();
+    Parser::new_from_str(yaml)
+        .load(&mut locations, false)
+        .map_err(syntax_error)?;
+    let documents = YamlLoader::load_from_str(yaml).map_err(syntax_error)?;
+
+    let fields = match documents.into_iter().next() {
+        Some(Yaml::Hash(hash)) => hash,
+        None | Some(Yaml::Null) => Default::default(),
+        Some(_) => eyre::bail!("Invalid front matter, it should be a mapping of fields"),
+    };
+    let fields = fields
+        .into_iter()
+        .filter_map(|(key, value)| Some((key.into_string()?, to_json(value))))
+        .collect::<serde_json::Map<_, _>>();
+
+    let start = Location { line: 2, column: 1 };
+    let mut problems = Vec::new();
+    for field in T::FIELDS {
+        let location = locations.values.get(field.name).copied().unwrap_or(start);
+        match fields.get(field.name) {
+            None if field.required => problems.push((
+                start,
+                format!(
+                    "missing field `{}`, it should be {}",
+                    field.name, field.expected
+                ),
+            )),
+            None => (),
+            Some(value) => {
+                if let Err(err) = (field.check)(value.clone()) {
+                    problems.push((
+                        location,
+                        format!(
+                            "`{}` at {location} should be {} ({err})",
+                            field.name, field.expected
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    let warnings = fields
+        .keys()
+        .filter(|name| !T::FIELDS.iter().any(|field| field.name == *name))
+        .map(|name| {
+            let location = locations.keys.get(name).copied().unwrap_or(start);
+            format!("Unknown front matter field `{name}` at {location}")
+        })
+        .collect();
+
+    if let Some(&(first, _)) = problems.first() {
+        let message = problems
+            .into_iter()
+            .map(|(_, problem)| problem)
+            .collect::<Vec<_>>()
+            // Aligned with the causes of the error report.
+            .join("\n     ");
+        let error = LineError {
+            line: first.line,
+            column: Some(first.column),
+            message,
+        };
+        return Err(eyre::Report::new(error).wrap_err("Invalid front matter"));
+    }
+
+    let front_matter = serde_json::from_value(Value::Object(fields))
+        .map_err(|err| eyre::eyre!(err).wrap_err("Invalid front matter"))?;
+    Ok((front_matter, warnings))
+}
+
+/// The YAML between the delimiters at the start of `content`, if there is any.
+fn split(content: &str) -> Option<&str> {
+    let rest = content.strip_prefix(DELIMITER)?;
+    let rest = rest
+        .strip_prefix("\r\n")
+        .or_else(|| rest.strip_prefix('\n'))?;
+
+    let mut end = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == DELIMITER {
+            return Some(&rest[..end]);
+        }
+        end += line.len();
+    }
+
+    None
+}
+
+/// Converts YAML to JSON, to deserialize it with `serde_json`.
+fn to_json(yaml: Yaml) -> Value {
+    match yaml {
+        Yaml::Real(real) => real
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map_or(Value::String(real), Value::Number),
+        Yaml::Integer(integer) => Value::from(integer),
+        Yaml::String(string) => Value::String(string),
+        Yaml::Boolean(boolean) => Value::Bool(boolean),
+        Yaml::Array(array) => Value::Array(array.into_iter().map(to_json).collect()),
+        Yaml::Hash(hash) => Value::Object(
+            hash.into_iter()
+                .filter_map(|(key, value)| Some((key.into_string()?, to_json(value))))
+                .collect(),
+        ),
+        Yaml::Alias(_) | Yaml::Null | Yaml::BadValue => Value::Null,
+    }
+}
+
+/// Locations of the keys and values of the top level mapping, collected from
+/// the events of the parser (the loaded [`Yaml`] doesn't keep them).
+#[derive(Default)]
+struct FieldLocations {
+    depth: usize,
+    /// Key whose value comes next, if in the top level mapping.
+    key: Option<String>,
+    keys: BTreeMap<String, Location>,
+    values: BTreeMap<String, Location>,
+}
+
+impl FieldLocations {
+    fn value(&mut self, marker: Marker) {
+        if let Some(key) = self.key.take() {
+            self.values.insert(key, Location::from_marker(marker));
+        }
+    }
+}
+
+impl MarkedEventReceiver for FieldLocations {
+    fn on_event(&mut self, event: Event, marker: Marker) {
+        match event {
+            Event::MappingStart(..) | Event::SequenceStart(..) => {
+                if self.depth == 1 {
+                    self.value(marker);
+                }
+                self.depth += 1;
+            }
+            Event::MappingEnd | Event::SequenceEnd => self.depth -= 1,
+            Event::Scalar(..) | Event::Alias(..) if self.depth == 1 && self.key.is_some() => {
+                self.value(marker);
+            }
+            Event::Scalar(key, ..) if self.depth == 1 => {
+                self.keys.insert(key.clone(), Location::from_marker(marker));
+                self.key = Some(key);
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        blog::BlogMetadata,
+        report::LineError,
+        testing::{self, entry, post},
+    };
+
+    /// The location of the first problem of the front matter of `content`, and
+    /// the message with all of them.
+    fn error(content: &str) -> (usize, Option<usize>, String) {
+        let err = parse::<BlogMetadata>(content).unwrap_err();
+        let error = err
+            .root_cause()
+            .downcast_ref::<LineError>()
+            .expect("Front matter errors point to a line");
+        (error.line, error.column, error.message.clone())
+    }
+
+    #[test]
+    fn diagnostics() {
+        let (line, column, message) = error("---\ntitle: Post\ndate: yesterday\n---\n");
+        assert_eq!((line, column), (3, Some(7)), "Should point to the value");
+        assert!(
+            message.contains("`date` at line 3 column 7 should be a date like 2024-01-31"),
+            "Unexpected message: {message}"
+        );
+
+        let (line, column, message) = error("---\ndate: 2024-01-31\n---\n");
+        assert_eq!((line, column), (2, Some(1)), "Should point to the start");
+        assert!(
+            message.contains("missing field `title`, it should be a string"),
+            "Unexpected message: {message}"
+        );
+
+        let content = post("Post", "tpoics: [rust]\n", "");
+        let (_, warnings) = parse::<BlogMetadata>(&content).unwrap();
+        let expected = ["Unknown front matter field `tpoics` at line 4 column 1"];
+        assert_eq!(warnings, expected);
+
+        // Kept with the entry, so that they're shown even if it's cached.
+        let entry = entry("typo", &content, &testing::config());
+        assert_eq!(entry.warnings, expected);
+    }
+}
@@ -0,0 +1,241 @@
+//! Syntax highlighting of code blocks, at build time.
+//!
+//! Code is split in tokens with [`syntect`], and each token gets the classes
+//! of its scopes (prefixed with `hl-`). The colors are in a separate
+//! stylesheet with a dark and a light theme, which follows the `data-theme`
+//! of the theme switcher, so pages don't need any script for it.
+//!
+//! The info string of a code fence can have some options after the language:
+//!
+//! ````markdown
+//! ```rust title="src/main.rs" numbers hl=2,4-6
+//! ````
+//!
+//! - `title`: shown as a caption above the code (quoted if it has spaces).
+//! - `numbers`: shows the number of each line.
+//! - `hl`: lines to highlight, as a list of numbers and ranges.
+//!
+//! Invalid options and unknown languages don't stop the build, but they are
+//! returned as warnings (which blog posts keep, to show them in every build).
+
+use color_eyre::eyre::{self, OptionExt as _};
+use maud::{Markup, PreEscaped, html};
+use std::{ops::RangeInclusive, sync::LazyLock};
+use syntect::{
+    highlighting::ThemeSet,
+    html::{ClassStyle, ClassedHTMLGenerator, css_for_theme_with_class_style},
+    parsing::{SyntaxReference, SyntaxSet},
+    util::LinesWithEndings,
+};
+
+/// Path of the stylesheet with the colors, relative to the output directory.
+pub const STYLESHEET_PATH: &str = "static/highlight.css";
+
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+
+/// Theme of the dark mode, which is the default.
+const DARK_THEME: &str = "base16-ocean.dark";
+const LIGHT_THEME: &str = "InspiredGitHub";
+/// Selector of the light mode, set by the theme switcher.
+const LIGHT_SELECTOR: &str = "html[data-theme=\"light\"]";
+
+static SYNTAXES: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+
+/// Options of a code block, from the info string of its fence.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Fence {
+    pub language: Option<String>,
+    pub title: Option<String>,
+    pub line_numbers: bool,
+    /// Lines to highlight, starting at 1.
+    pub highlighted: Vec<RangeInclusive<usize>>,
+}
+
+impl Fence {
+    /// Parses the info string of a fence, adding a warning to `warnings` for
+    /// each option that isn't valid (which are ignored).
+    pub fn parse(info: &str, warnings: &mut Vec<String>) -> Self {
+        let mut words = split_words(info).into_iter();
+        let mut fence = Self {
+            language: words.next(),
+            ..Self::default()
+        };
+
+        for word in words {
+            match word.split_once('=') {
+                None if word == "numbers" => fence.line_numbers = true,
+                Some(("title", title)) => fence.title = Some(title.to_string()),
+                Some(("hl", lines)) => match parse_ranges(lines) {
+                    Some(ranges) => fence.highlighted.extend(ranges),
+                    None => warnings.push(format!(
+                        "invalid lines to highlight `{lines}`, they should be like `2,4-6`"
+                    )),
+                },
+                _ => warnings.push(format!("unknown option `{word}`")),
+            }
+        }
+
+        fence
+    }
+
+    fn is_highlighted(&self, line: usize) -> bool {
+        self.highlighted.iter().any(|range| range.contains(&line))
+    }
+}
+
+/// Splits `info` by whitespace, keeping quoted text together (without the
+/// quotes).
+fn split_words(info: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in info.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Parses ranges of lines like `2,4-6`.
+fn parse_ranges(ranges: &str) -> Option<Vec<RangeInclusive<usize>>> {
+    ranges
+        .split(',')
+        .map(|range| {
+            let (start, end) = range.split_once('-').unwrap_or((range, range));
+            let (start, end) = (start.trim().parse().ok()?, end.trim().parse().ok()?);
+            (1 <= start && start <= end).then_some(start..=end)
+        })
+        .collect()
+}
+
+/// Renders `code` as a highlighted block with the options of `fence`, adding
+/// to `warnings` if it can't be highlighted.
+pub fn render(code: &str, fence: &Fence, warnings: &mut Vec<String>) -> Markup {
+    let syntax = match &fence.language {
+        Some(language) => SYNTAXES.find_syntax_by_token(language).unwrap_or_else(|| {
+            warnings.push(format!(
+                "unknown language `{language}`, it isn't highlighted"
+            ));
+            SYNTAXES.find_syntax_plain_text()
+        }),
+        None => SYNTAXES.find_syntax_plain_text(),
+    };
+
+    let lines = highlight(code, syntax).unwrap_or_else(|err| {
+        warnings.push(format!("couldn't highlight it: {err}"));
+        code.lines().map(|line| html! { (line) }.0).collect()
+    });
+
+    let block = html! {
+        pre."hl-code"."line-numbers"[fence.line_numbers] {
+            code class=[fence.language.as_ref().map(|language| format!("language-{language}"))] {
+                @for (i, line) in lines.iter().enumerate() {
+                    span."line"."highlighted"[fence.is_highlighted(i + 1)] { (PreEscaped(line)) }
+                }
+            }
+        }
+    };
+
+    html! {
+        @if let Some(title) = &fence.title {
+            figure."code-block" {
+                figcaption { (title) }
+                (block)
+            }
+        } @else {
+            (block)
+        }
+    }
+}
+
+/// The HTML of each line of `code`, highlighted as `syntax`.
+fn highlight(code: &str, syntax: &SyntaxReference) -> eyre::Result<Vec<String>> {
+    let mut generator = ClassedHTMLGenerator::new_with_class_style(syntax, &SYNTAXES, CLASS_STYLE);
+    for line in LinesWithEndings::from(code) {
+        generator.parse_html_for_line_which_includes_newline(line)?;
+    }
+
+    Ok(split_lines(&generator.finalize()))
+}
+
+/// Splits highlighted `html` in lines, closing the spans that are open at the
+/// end of each line and opening them again in the next one, so that each line
+/// can be wrapped in its own element.
+fn split_lines(html: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut open = Vec::<&str>::new();
+    let mut line = String::new();
+    let mut has_text = false;
+    let mut rest = html;
+
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("</span>") {
+            open.pop();
+            line.push_str("</span>");
+            rest = after;
+        } else if rest.starts_with("<span") {
+            let end = rest.find('>').map_or(rest.len(), |end| end + 1);
+            open.push(&rest[..end]);
+            line.push_str(&rest[..end]);
+            rest = &rest[end..];
+        } else if c == '\n' {
+            line.push_str(&"</span>".repeat(open.len()));
+            lines.push(std::mem::replace(&mut line, open.concat()));
+            has_text = false;
+            rest = &rest[1..];
+        } else {
+            line.push(c);
+            has_text = true;
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    // Code that ends in a newline doesn't have another line after it.
+    if has_text || lines.is_empty() {
+        line.push_str(&"</span>".repeat(open.len()));
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Stylesheet with the colors of the highlighted code, for both themes.
+pub fn stylesheet() -> eyre::Result<String> {
+    let themes = ThemeSet::load_defaults();
+    let css = |name: &str| -> eyre::Result<String> {
+        let theme = themes
+            .themes
+            .get(name)
+            .ok_or_eyre(format!("Missing theme {name:?}"))?;
+        Ok(css_for_theme_with_class_style(theme, CLASS_STYLE)?)
+    };
+
+    let dark = css(DARK_THEME)?;
+    let light = css(LIGHT_THEME)?
+        .lines()
+        .map(|line| match line.strip_suffix(" {") {
+            // Only rules end in `{`, and their selectors are separated by `, `.
+            Some(selectors) => {
+                let selectors = selectors
+                    .split(", ")
+                    .map(|selector| format!("{LIGHT_SELECTOR} {selector}"))
+                    .collect::<Vec<_>>();
+                format!("{} {{\n", selectors.join(", "))
+            }
+            None => format!("{line}\n"),
+        })
+        .collect::<String>();
+
+    Ok(format!("{dark}\n{light}"))
+}
@@ -1,20 +1,124 @@
+use jiff::civil::Date;
 use maud::{DOCTYPE, Markup, html};
+use serde::Serialize;
+use url::Url;
+
+use crate::config::SiteConfig;
 
 pub mod blog;
+pub mod cache;
+pub mod collection;
 pub mod components;
+pub mod config;
+pub mod front_matter;
+pub mod highlight;
+pub mod links;
+pub mod math;
 pub mod media;
 pub mod pics;
 pub mod projects;
+pub mod report;
+pub mod search;
+pub mod serve;
+pub mod sitemap;
+#[cfg(test)]
+mod testing;
+
+/// Metadata of a page, used for the `<head>` (title, link previews...) and the
+/// sitemap.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PageMeta {
+    /// Title of the page, the site title is used if there is none.
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Image for link previews, either absolute or relative to the site root.
+    pub image: Option<String>,
+    /// When the page was last modified.
+    pub date: Option<Date>,
+    /// Whether the page is an article (e.g. a blog post), instead of a more
+    /// general page of the site.
+    pub article: bool,
+    /// Whether the page is only reachable by its link, so it's left out of
+    /// the sitemap and search engines.
+    pub unlisted: bool,
+    /// Canonical URL of the page, set by the generator from its path.
+    pub canonical: Option<Url>,
+    /// Whether the page has math, so it needs the KaTeX stylesheet.
+    pub math: bool,
+    /// Whether the page has highlighted code, so it needs its stylesheet.
+    pub code: bool,
+}
+
+impl PageMeta {
+    pub fn titled(title: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            title: Some(title.into()),
+            description: Some(description.into()),
+            ..Default::default()
+        }
+    }
+}
+
+pub fn shell(config: &SiteConfig, meta: &PageMeta, content: Markup) -> Markup {
+    let title = match &meta.title {
+        Some(title) => format!("{title} | {}", config.title),
+        None => config.title.clone(),
+    };
+    let image = meta.image.as_deref().map(|image| config.url(image));
 
-pub fn shell(content: Markup) -> Markup {
     html! {
         (DOCTYPE)
         html {
             head {
                 meta charset="UTF-8" {}
                 meta name="viewport" content="width=device-width, initial-scale=1.0" {}
-                title { "odilf's site" }
+                title { (title) }
+                @if let Some(description) = &meta.description {
+                    meta name="description" content=(description) {}
+                }
+                @if meta.unlisted {
+                    meta name="robots" content="noindex" {}
+                }
+                @if let Some(canonical) = &meta.canonical {
+                    link rel="canonical" href=(canonical) {}
+                }
+
+                meta property="og:site_name" content=(config.title) {}
+                meta property="og:title" content=(meta.title.as_deref().unwrap_or(&config.title)) {}
+                @if let Some(description) = &meta.description {
+                    meta property="og:description" content=(description) {}
+                }
+                @if let Some(canonical) = &meta.canonical {
+                    meta property="og:url" content=(canonical) {}
+                }
+                @if meta.article {
+                    meta property="og:type" content="article" {}
+                    @if let Some(date) = meta.date {
+                        meta property="article:published_time" content=(date) {}
+                    }
+                } @else {
+                    meta property="og:type" content="website" {}
+                }
+                @if let Some(image) = &image {
+                    meta property="og:image" content=(image) {}
+                }
+
+                meta name="twitter:card" content=(if image.is_some() { "summary_large_image" } else { "summary" }) {}
+                meta name="twitter:title" content=(meta.title.as_deref().unwrap_or(&config.title)) {}
+                @if let Some(description) = &meta.description {
+                    meta name="twitter:description" content=(description) {}
+                }
+                @if let Some(image) = &image {
+                    meta name="twitter:image" content=(image) {}
+                }
+
                 link href="/static/app.css" rel="stylesheet" {}
+                @if meta.math {
+                    link rel="stylesheet" href=(math::STYLESHEET_HREF) {}
+                }
+                @if meta.code {
+                    link rel="stylesheet" href=(format!("/{}", highlight::STYLESHEET_PATH)) {}
+                }
 
                 link rel="icon" href="/favicon.svg" {}
                 link rel="icon" href="/favicon.png" {}
@@ -26,6 +130,9 @@ pub fn shell(content: Markup) -> Markup {
             body {
                 main."font-mono py-4 content" { (content) }
                 (components::theme_script())
+                @if config.build.live_reload {
+                    (components::live_reload_script())
+                }
             }
         }
     }
@@ -38,6 +145,7 @@ pub fn home() -> Markup {
         ("/media-log", "media log", media::DESC),
         ("/projects", "projects", projects::DESC),
         ("/about", "about", "information about me and CV"),
+        ("/search", "search", search::DESC),
     ];
 
     html! {
@@ -88,3 +196,12 @@ pub fn about() -> Markup {
         }
     }
 }
+
+pub fn not_found() -> Markup {
+    html! {
+        ."faint" { a href="/" { "<-- (home)" } }
+        h1 { "404" }
+        p."faint" { "there's nothing here, the page might have moved or never existed." }
+        p."pt-4" { a href="/" { "> go back home" } }
+    }
+}
@@ -0,0 +1,177 @@
+//! Checker for broken internal links in the generated site.
+//!
+//! Every HTML file in the output directory is parsed, and each `href` and `src`
+//! that points inside the site is resolved the same way the development server
+//! does (see [`crate::serve`]). Links with a `#fragment` also need an element
+//! with that `id` in the page they point to.
+
+use color_eyre::eyre::{self, WrapErr as _};
+use rayon::prelude::*;
+use scraper::{Html, Selector};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+use url::Url;
+
+use crate::{config::SiteConfig, serve};
+
+/// A reference in a generated page that doesn't resolve.
+#[derive(Debug)]
+pub struct BrokenLink {
+    /// Page with the link, relative to the output directory.
+    pub page: PathBuf,
+    /// The link as written in the page.
+    pub link: String,
+    pub problem: Problem,
+}
+
+#[derive(Debug)]
+pub enum Problem {
+    /// The target file doesn't exist.
+    MissingFile,
+    /// The target page exists but has no element with this `id`.
+    MissingFragment(String),
+}
+
+impl fmt::Display for BrokenLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            page,
+            link,
+            problem,
+        } = self;
+        match problem {
+            Problem::MissingFile => write!(f, "{page:?} links to {link:?}, which doesn't exist"),
+            Problem::MissingFragment(id) => write!(
+                f,
+                "{page:?} links to {link:?}, but there is no element with id {id:?}"
+            ),
+        }
+    }
+}
+
+/// The ids and links of a parsed page.
+#[derive(Debug, Default)]
+struct Page {
+    ids: HashSet<String>,
+    links: Vec<String>,
+}
+
+/// Finds all the broken internal links in the output directory of `config`.
+///
+/// External links are not checked.
+pub fn check(config: &SiteConfig) -> eyre::Result<Vec<BrokenLink>> {
+    let output = &config.output;
+    let mut files = Vec::new();
+    html_files(output, Path::new(""), &mut files)?;
+
+    let pages = files
+        .into_par_iter()
+        .map(|file| {
+            let path = output.join(&file);
+            let content =
+                fs::read_to_string(&path).wrap_err_with(|| format!("Couldn't read {path:?}"))?;
+            Ok((file, parse(&content)))
+        })
+        .collect::<eyre::Result<BTreeMap<_, _>>>()?;
+
+    let pages = &pages;
+    let broken = pages
+        .par_iter()
+        .flat_map_iter(|(file, page)| {
+            let page_url = config.url(&file.to_string_lossy());
+            page.links.iter().filter_map(move |link| {
+                let problem = check_link(config, pages, &page_url, link)?;
+                Some(BrokenLink {
+                    page: file.clone(),
+                    link: link.clone(),
+                    problem,
+                })
+            })
+        })
+        .collect();
+
+    Ok(broken)
+}
+
+/// Collects the paths (relative to `root`) of all the HTML files in `root/dir`.
+fn html_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> eyre::Result<()> {
+    let path = root.join(dir);
+    for entry in fs::read_dir(&path).wrap_err_with(|| format!("Couldn't read {path:?}"))? {
+        let entry = entry?;
+        let relative = dir.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            html_files(root, &relative, files)?;
+        } else if relative.extension().is_some_and(|ext| ext == "html") {
+            files.push(relative);
+        }
+    }
+
+    Ok(())
+}
+
+fn parse(content: &str) -> Page {
+    let document = Html::parse_document(content);
+    let with_id = Selector::parse("[id], a[name]").expect("Selector is valid");
+    let with_link = Selector::parse("[href], [src]").expect("Selector is valid");
+
+    let ids = document
+        .select(&with_id)
+        .filter_map(|element| {
+            let element = element.value();
+            element.id().or_else(|| element.attr("name"))
+        })
+        .map(str::to_string)
+        .collect();
+
+    let links = document
+        .select(&with_link)
+        .flat_map(|element| {
+            let element = element.value();
+            [element.attr("href"), element.attr("src")]
+        })
+        .flatten()
+        .map(str::to_string)
+        .collect();
+
+    Page { ids, links }
+}
+
+/// Checks a single link of the page at `page_url`, returning what's wrong with it.
+fn check_link(
+    config: &SiteConfig,
+    pages: &BTreeMap<PathBuf, Page>,
+    page_url: &Url,
+    link: &str,
+) -> Option<Problem> {
+    // Unparseable links are left for the browser to deal with.
+    let url = page_url.join(link.trim()).ok()?;
+
+    // Other schemes (`mailto:`, `data:`...) and other hosts have different origins.
+    let base = &config.base_url;
+    if url.origin() != base.origin() {
+        return None;
+    }
+    let Some(path) = url.path().strip_prefix(base.path()) else {
+        return Some(Problem::MissingFile);
+    };
+
+    let Some(file) = serve::resolve(&config.output, path) else {
+        return Some(Problem::MissingFile);
+    };
+
+    let id = url.fragment().filter(|fragment| !fragment.is_empty())?;
+    let id = percent_encoding::percent_decode_str(id).decode_utf8_lossy();
+    let relative = file.strip_prefix(&config.output).ok()?;
+
+    // Only HTML pages have ids, fragments of other files (e.g. `#page=2` in
+    // PDFs) mean something else.
+    let page = pages.get(relative)?;
+    if page.ids.contains(&*id) {
+        None
+    } else {
+        Some(Problem::MissingFragment(id.into_owned()))
+    }
+}
@@ -1,244 +1,844 @@
+use clap::{Parser, Subcommand, ValueEnum};
 use color_eyre::eyre::{self, ContextCompat, WrapErr as _};
-use maud::{Markup, Render};
+use jiff::tz::TimeZone;
+use maud::Markup;
 use odilf_site::{
-    about,
-    blog::{self, BlogEntry},
-    home,
-    media::{self, MediaLog},
-    pics, projects, shell,
+    PageMeta, about,
+    blog::BlogEntry,
+    cache::{BuildCache, Hash},
+    collection::{self, Collection, DerivedOutput},
+    config::{self, BuildOptions, SiteConfig},
+    highlight, home, links, math,
+    media::MediaLog,
+    not_found, pics,
+    projects::{self, Projects},
+    report::ErrorReport,
+    search::{self, DocumentRegistry, SearchIndex},
+    serve, shell,
+    sitemap::{self, PageRegistry},
 };
+use rayon::prelude::*;
+use serde::Serialize;
 use std::{
-    cmp::Reverse,
     fs, io,
     path::{Path, PathBuf},
     process::Command,
+    sync::{Mutex, OnceLock},
 };
 
+/// Generator of odilf.com
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Path to the site configuration.
+    #[arg(long, global = true, env = "ODILF_SITE_CONFIG", default_value = config::DEFAULT_PATH)]
+    config: PathBuf,
+
+    /// Directory where the site is generated, instead of the one in the configuration.
+    #[arg(long, short, global = true)]
+    output: Option<PathBuf>,
+
+    /// Build for deployment, fetching fresh remote data. Served release builds
+    /// don't include drafts nor live reload.
+    #[arg(long, global = true)]
+    release: bool,
+
+    /// Include drafts, which otherwise are only included by `serve`.
+    #[arg(long, global = true)]
+    drafts: bool,
+
+    /// Don't access the network, using only cached remote data.
+    #[arg(long, global = true)]
+    offline: bool,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Commands {
+    /// Build the site (the default).
+    Build {
+        /// Only build these sections.
+        #[arg(long, value_delimiter = ',')]
+        section: Vec<Section>,
+        /// Fail if there are broken internal links, instead of only warning.
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Build the site, serve it locally and rebuild it whenever the content changes.
+    Serve {
+        #[arg(long, default_value = "127.0.0.1:5173")]
+        address: String,
+    },
+    /// Check that all the content is valid, without generating anything.
+    Check,
+    /// Create a new blog post or media log entry.
+    New {
+        kind: EntryKind,
+        slug: String,
+        /// Title of the entry, the slug by default.
+        #[arg(long)]
+        title: Option<String>,
+    },
+    /// Fetch the projects from GitHub into a new projects file.
+    FetchProjects {
+        /// Projects to fetch. Defaults to the ones already in the projects file.
+        names: Vec<String>,
+        /// File to write the fetched projects to.
+        #[arg(long, default_value = "projects-fetched.toml")]
+        destination: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Section {
+    Home,
+    About,
+    Blog,
+    Projects,
+    MediaLog,
+    Pics,
+    /// The search page (the index is made in full builds).
+    Search,
+    /// Stylesheet and favicons.
+    Assets,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum EntryKind {
+    Blog,
+    MediaLog,
+}
+
 fn main() -> eyre::Result<()> {
     dotenvy::dotenv()?;
     tracing_subscriber::fmt()
         .with_max_level(tracing::Level::INFO)
         .init();
 
-    let output = {
-        #[cfg(debug_assertions)]
-        const DEFAULT_PATH: &str = "./target/debug/site";
-        #[cfg(not(debug_assertions))]
-        const DEFAULT_PATH: &str = "./target/release/site";
+    let cli = Cli::parse();
+    let command = cli.command.unwrap_or(Commands::Build {
+        section: Vec::new(),
+        strict: false,
+    });
+
+    let options = BuildOptions::from_flags(
+        cli.release,
+        cli.drafts,
+        cli.offline,
+        matches!(command, Commands::Serve { .. }),
+        jiff::Zoned::now().date(),
+    );
+    let mut config = SiteConfig::load(&cli.config, options)?;
+    if let Some(output) = cli.output {
+        config.output = output;
+    }
 
-        let relative =
-            std::env::var("ODILF_OUTPUT_PATH").unwrap_or_else(|_| DEFAULT_PATH.to_string());
+    match command {
+        Commands::Build { section, strict } => {
+            tracing::info!(output=?config.output);
+            build(&config, &section)?;
+            check_links(&config, strict)
+        }
+        Commands::Serve { address } => {
+            // When serving, a failed build shouldn't stop the server; the error
+            // can be fixed and it will be rebuilt.
+            if let Err(err) = build(&config, &[]) {
+                tracing::error!(?err, "Initial build failed");
+            }
 
-        std::env::current_dir()?.join(relative)
+            let watch = [
+                config.blog.path.clone(),
+                config.media_log.path.clone(),
+                PathBuf::from("public"),
+            ];
+            serve::serve(&config.output, &address, &watch, || {
+                build(&config, &[])?;
+                check_links(&config, false)
+            })
+        }
+        Commands::Check => check(&config),
+        Commands::New { kind, slug, title } => new_entry(&config, kind, &slug, title),
+        Commands::FetchProjects { names, destination } => {
+            fetch_projects(&config, names, &destination)
+        }
+    }
+}
+
+/// Builds the given sections of the site, or all of them if `sections` is empty.
+fn build(config: &SiteConfig, sections: &[Section]) -> eyre::Result<()> {
+    fs::create_dir_all(&config.output)
+        .wrap_err_with(|| format!("Couldn't create output directory at {:?}", config.output))?;
+
+    let site = Site {
+        cache: BuildCache::load(config),
+        math_assets: OnceLock::new(),
+        pages: PageRegistry::default(),
+        stale: Mutex::default(),
+        errors: ErrorReport::default(),
+        search: DocumentRegistry::default(),
+        config,
     };
 
-    tracing::info!(?output);
+    let result = site.build(sections);
+    site.report_stale();
+    // Save the cache even if the build fails, to keep what was generated. Only
+    // forget unused pages if everything was built, though.
+    site.cache.save(sections.is_empty())?;
+
+    // Broken content only fails release builds, so that one bad draft doesn't
+    // stop the development server.
+    let content = report_content_errors(&site.errors, config.build.release);
+    result.and(content)
+}
+
+/// Logs all the errors of the content files together, failing if there are
+/// any and `fail` is set.
+fn report_content_errors(errors: &ErrorReport, fail: bool) -> eyre::Result<()> {
+    if errors.is_empty() {
+        return Ok(());
+    }
 
-    save_page("index.html", home(), &output)?;
-    save_page("about/index.html", about(), &output)?;
-    generate_blog(&output)?;
-    generate_projects(&output)?;
-    generate_media_log(&output)?;
-    generate_pics(&output)?;
-    generate_tailwind("static/app.css", &output)?;
-    copy_favicon(&output)?;
+    let count = errors.len();
+    tracing::error!("{count} content files have errors:\n{errors}");
+    if fail {
+        eyre::bail!("{count} content files have errors, see the report above");
+    }
 
+    tracing::warn!("The files with errors were skipped");
     Ok(())
 }
 
-fn save_page_no_shell(path: impl AsRef<Path>, page: Markup, output: &Path) -> eyre::Result<()> {
-    let path = path.as_ref();
-    fs::create_dir_all(output.join(path.parent().wrap_err("Couldn't get parent of path")?))?;
-    fs::write(output.join(path), page.0)
-        .wrap_err_with(|| format!("Couldn't write to page {path:?}"))
-}
+/// Checks the internal links of the generated site, failing if there are broken
+/// ones and `strict` is set.
+fn check_links(config: &SiteConfig, strict: bool) -> eyre::Result<()> {
+    let broken = links::check(config).wrap_err("Couldn't check links")?;
+    if broken.is_empty() {
+        tracing::info!("No broken links");
+        return Ok(());
+    }
+
+    for link in &broken {
+        if strict {
+            tracing::error!("{link}");
+        } else {
+            tracing::warn!("{link}");
+        }
+    }
+
+    if strict {
+        eyre::bail!("{} broken links, see the errors above", broken.len());
+    }
 
-fn save_page(path: impl AsRef<Path>, page: Markup, output: &Path) -> eyre::Result<()> {
-    save_page_no_shell(path, shell(page), output)
+    Ok(())
 }
 
-fn generate_blog(output: &Path) -> eyre::Result<()> {
-    let blog_path = PathBuf::from(
-        std::env::var("ODILF_BLOG_PATH")
-            .wrap_err("Couldn't get `ODILF_BLOG_PATH` env variable.")?,
-    );
+/// Parses all the content, reporting every invalid file.
+fn check(config: &SiteConfig) -> eyre::Result<()> {
+    let errors = ErrorReport::default();
+    let mut count = check_collection::<BlogEntry>(config, &errors)?;
+    count += check_collection::<MediaLog>(config, &errors)?;
+
+    let projects = &config.projects.path;
+    let result = fs::read_to_string(projects)
+        .map_err(eyre::Report::from)
+        .and_then(|content| Ok(toml::from_str::<Projects>(&content).map(drop)?))
+        .wrap_err("Invalid projects file");
+    if let Err(err) = result {
+        errors.push(projects, err);
+    }
+    count += 1;
 
-    let blog_output = output.join("blog");
-    fs::create_dir_all(&blog_output)
-        .wrap_err_with(|| format!("Couldn't create blog path at {blog_output:?}"))?;
+    report_content_errors(&errors, true)?;
+    tracing::info!("All {count} files are valid");
 
-    tracing::info!(?blog_output, ?blog_path);
+    Ok(())
+}
 
-    let mut referenced_urls = Vec::new();
-    let mut blog_entries = fs::read_dir(&blog_path)?
-        .map(|entry| {
-            let entry = entry?;
-            let mut path = entry.path();
-            if path.is_dir() {
-                tracing::debug!("Skipping directory");
-                return eyre::Ok(None);
+/// Parses every entry of the collection `T`, adding the invalid ones to
+/// `errors` and returning how many files there are.
+fn check_collection<T: Collection>(
+    config: &SiteConfig,
+    errors: &ErrorReport,
+) -> eyre::Result<usize> {
+    let paths = markdown_files(T::source_dir(config))?;
+    paths.par_iter().for_each(|path| {
+        let _span = tracing::warn_span!("entry", file = %path.display()).entered();
+        let result = path
+            .file_stem()
+            .and_then(|name| name.to_str())
+            .wrap_err("Couldn't get file name")
+            .and_then(|slug| {
+                let content = fs::read_to_string(path)?;
+                T::check(slug, &content, config)
+            })
+            .wrap_err_with(|| format!("Invalid {}", T::NAME));
+
+        match result {
+            Ok(warnings) => {
+                for warning in warnings {
+                    tracing::warn!("{warning}");
+                }
             }
+            Err(err) => errors.push(path, err),
+        }
+    });
 
-            if path.extension().and_then(|ext| ext.to_str()) != Some("md") {
-                tracing::info!("Skipping non `.md` file");
-                return Ok(None);
-            }
+    Ok(paths.len())
+}
 
-            tracing::debug!(?path, "Reading blog entry");
+/// Creates a new markdown file for a blog post or media log entry.
+fn new_entry(
+    config: &SiteConfig,
+    kind: EntryKind,
+    slug: &str,
+    title: Option<String>,
+) -> eyre::Result<()> {
+    // Quoted as JSON, which is also valid YAML.
+    let title = serde_json::to_string(title.as_deref().unwrap_or(slug))?;
+    let today = jiff::Zoned::now().date();
+
+    let (dir, front_matter) = match kind {
+        EntryKind::Blog => (
+            &config.blog.path,
+            format!("title: {title}\ndate: {today}\nstatus: draft\ntopics: []"),
+        ),
+        EntryKind::MediaLog => (
+            &config.media_log.path,
+            format!("title: {title}\ntype: book\nrating: 0\ndate: {today}\nurls: []"),
+        ),
+    };
 
-            let post_content = fs::read_to_string(&path).wrap_err("Couldn't read blog post")?;
+    let path = dir.join(format!("{slug}.md"));
+    if path.exists() {
+        eyre::bail!("{path:?} already exists");
+    }
 
-            path.set_extension("");
-            let slug = path
-                .file_name()
-                .and_then(|name| name.to_str())
-                .wrap_err("Couldn't get file name")?;
+    fs::write(&path, format!("---\n{front_matter}\n---\n\n"))
+        .wrap_err_with(|| format!("Couldn't create {path:?}"))?;
+    tracing::info!(?path, "Created new entry");
 
-            let Some(entry) =
-                BlogEntry::from_slug_and_content(slug, &post_content, &mut referenced_urls)
-                    .wrap_err("Couldn't form blog post")?
-            else {
-                return Ok(None);
-            };
+    Ok(())
+}
 
-            tracing::info!(?slug, "Generating blog page");
+/// Fetches the projects with the given names from GitHub (or all the ones in
+/// the projects file if there are none) and writes them to `destination`.
+fn fetch_projects(
+    config: &SiteConfig,
+    mut names: Vec<String>,
+    destination: &Path,
+) -> eyre::Result<()> {
+    if config.build.offline {
+        eyre::bail!("Can't fetch projects in offline mode");
+    }
 
-            // TODO: This shouldn't need to allocate
-            save_page(
-                format!("blog/{}/index.html", entry.slug),
-                entry.render(),
-                output,
-            )?;
+    if names.is_empty() {
+        let src = &config.projects.path;
+        let current: Projects = toml::from_str(&fs::read_to_string(src)?)
+            .wrap_err_with(|| format!("Couldn't read projects from {src:?}"))?;
+        names = current.iter().map(|(name, _)| name.clone()).collect();
+    }
 
-            Ok(Some(entry))
-        })
-        .flat_map(|result| match result {
-            Ok(Some(entry)) => Some(entry),
-            Ok(None) => None,
-            Err(err) => {
-                tracing::error!(?err);
-                None
+    let projects = names
+        .iter()
+        .map(|project_name| projects::fetch::get_github_single(project_name))
+        .collect::<eyre::Result<_>>()?;
+
+    let toml = toml::to_string_pretty(&Projects::new(projects))?;
+    fs::write(destination, toml)
+        .wrap_err_with(|| format!("Couldn't write projects to {destination:?}"))?;
+    tracing::info!(?destination, "Fetched {} projects", names.len());
+
+    Ok(())
+}
+
+/// State shared by all the generators during a build.
+///
+/// Sections and entries are generated in parallel, so this is only accessed
+/// through shared references.
+struct Site<'a> {
+    config: &'a SiteConfig,
+    cache: BuildCache,
+    /// Pages generated with [`Site::save_page`], for the sitemap.
+    pages: PageRegistry,
+    /// Remote data that couldn't be fetched in an offline build.
+    stale: Mutex<Vec<String>>,
+    /// Errors of the content files, reported at the end of the build.
+    errors: ErrorReport,
+    /// Documents of the search index, registered by the collections.
+    search: DocumentRegistry,
+    /// Whether the KaTeX stylesheet and fonts are in the output, so that pages
+    /// with math can link them. See [`Site::math_assets`].
+    math_assets: OnceLock<bool>,
+}
+
+type Generator = fn(&Site) -> eyre::Result<()>;
+
+impl Site<'_> {
+    /// Generates the given sections of the site in parallel, or all of them
+    /// if `sections` is empty.
+    fn build(&self, sections: &[Section]) -> eyre::Result<()> {
+        let generators: [(Section, &str, Generator); 12] = [
+            (Section::Home, "home", |site| {
+                site.save_page("index.html", PageMeta::default(), &(), home)
+            }),
+            (Section::Home, "404 page", |site| {
+                // Not a real page, so it's not registered in the sitemap.
+                let meta = PageMeta {
+                    title: Some("not found".to_string()),
+                    ..Default::default()
+                };
+                site.save_page_no_shell("404.html", &meta, || {
+                    Ok(shell(site.config, &meta, not_found()))
+                })
+            }),
+            (Section::About, "about", |site| {
+                let meta = PageMeta::titled("about", "information about me and CV");
+                site.save_page("about/index.html", meta, &(), about)
+            }),
+            (Section::Blog, "blog", generate_collection::<BlogEntry>),
+            (Section::Projects, "projects", generate_projects),
+            (
+                Section::MediaLog,
+                "media log",
+                generate_collection::<MediaLog>,
+            ),
+            (Section::Pics, "pics", generate_pics),
+            (Section::Search, "search", |site| {
+                let meta = PageMeta::titled("search", search::DESC);
+                site.save_page("search/index.html", meta, &(), search::page)
+            }),
+            (Section::Assets, "tailwind", |site| {
+                generate_tailwind("static/app.css", &site.config.output)
+            }),
+            (Section::Assets, "highlighting stylesheet", |site| {
+                site.save_file(highlight::STYLESHEET_PATH, &(), highlight::stylesheet)
+            }),
+            (Section::Assets, "favicon", |site| {
+                copy_favicon(&site.config.output)
+            }),
+            (Section::Assets, "placeholder cover", |site| {
+                copy_placeholder_cover(&site.config.output)
+            }),
+        ];
+
+        let results = generators
+            .par_iter()
+            .filter(|(section, _, _)| sections.is_empty() || sections.contains(section))
+            .map(|(_, name, generate)| {
+                generate(self).wrap_err_with(|| format!("Couldn't generate {name}"))
+            })
+            .collect::<Vec<_>>();
+
+        report_errors("sections", results)?;
+
+        // The sitemap and the search index need every page, so they're only
+        // made in full builds.
+        if sections.is_empty() {
+            self.generate_sitemap()?;
+            self.generate_search_index()?;
+        }
+
+        Ok(())
+    }
+
+    /// Whether the KaTeX stylesheet and fonts are in the output.
+    ///
+    /// They're only fetched and copied for the first page with math, so that
+    /// builds without math never download them. If they can't be fetched, the
+    /// build goes on without them, and pages don't link the stylesheet.
+    fn math_assets(&self) -> bool {
+        *self.math_assets.get_or_init(|| {
+            let copied = math::fetch_assets(self.config).and_then(|fetched| {
+                if fetched {
+                    math::copy_assets(self.config)?;
+                }
+                Ok(fetched)
+            });
+
+            match copied {
+                Ok(true) => true,
+                Ok(false) => {
+                    self.mark_stale("KaTeX stylesheet and fonts".to_string());
+                    false
+                }
+                Err(err) => {
+                    tracing::warn!(?err, "Pages with math won't have the KaTeX stylesheet");
+                    false
+                }
             }
         })
-        .collect::<Vec<_>>();
+    }
 
-    blog_entries.sort_by_key(|blog| Reverse(blog.metadata.date));
-    save_page("blog/index.html", blog::home(blog_entries.iter()), output)?;
-    save_page_no_shell(
-        "blog/rss.xml",
-        blog::feed::rss(blog_entries.iter())?,
-        output,
-    )?;
-    save_page_no_shell(
-        "blog/atom.xml",
-        blog::feed::atom(blog_entries.iter())?,
-        output,
-    )?;
+    /// Notes that `what` is missing or outdated because of an offline build.
+    fn mark_stale(&self, what: String) {
+        self.stale
+            .lock()
+            .expect("Stale list lock shouldn't be poisoned")
+            .push(what);
+    }
+
+    /// Logs everything that was marked as stale during the build.
+    fn report_stale(&self) {
+        let mut stale = self
+            .stale
+            .lock()
+            .expect("Stale list lock shouldn't be poisoned");
+        if stale.is_empty() {
+            return;
+        }
 
-    for url in referenced_urls {
-        let src = blog_path.join(&url);
-        let dst = blog_output.join(&url);
-        fs::copy(src, dst).wrap_err_with(|| format!("Couldn't copy referenced url ({url})"))?;
+        stale.sort();
+        tracing::warn!(
+            "Offline build, {} things are missing remote data:\n  - {}",
+            stale.len(),
+            stale.join("\n  - ")
+        );
     }
 
-    Ok(())
-}
+    fn generate_sitemap(&self) -> eyre::Result<()> {
+        let pages = self.pages.pages();
+        tracing::info!("Generating sitemap with {} pages", pages.len());
+        self.save_file("sitemap.xml", &pages, || {
+            Ok(sitemap::sitemap(self.config, &pages).0)
+        })?;
+        self.save_file("robots.txt", &(), || Ok(sitemap::robots(self.config)))
+    }
 
-fn generate_projects(output: &Path) -> eyre::Result<()> {
-    let src = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("projects.toml");
+    fn generate_search_index(&self) -> eyre::Result<()> {
+        let documents = self.search.documents();
+        tracing::info!("Generating search index with {} documents", documents.len());
+        self.save_file(search::INDEX_PATH, &documents, || {
+            serde_json::to_string(&SearchIndex::new(&documents))
+                .wrap_err("Couldn't serialize search index")
+        })
+    }
 
-    let project_data =
-        toml::from_str(&fs::read_to_string(src)?).wrap_err("Couldn't read projects.toml")?;
+    /// Writes the file at `path` (relative to the output) with the result of
+    /// `render`, unless the build cache says that it was already generated
+    /// from the same `inputs`.
+    fn save_file<C: AsRef<[u8]>>(
+        &self,
+        path: impl AsRef<Path>,
+        inputs: &impl Serialize,
+        render: impl FnOnce() -> eyre::Result<C>,
+    ) -> eyre::Result<()> {
+        let path = path.as_ref();
+        let inputs = Hash::of(inputs);
+        if self.cache.is_fresh(path, inputs) {
+            tracing::debug!(?path, "Skipping up-to-date page");
+            return Ok(());
+        }
 
-    save_page("projects/index.html", projects::home(project_data), output)?;
+        let output = &self.config.output;
+        fs::create_dir_all(output.join(path.parent().wrap_err("Couldn't get parent of path")?))?;
+        fs::write(output.join(path), render()?)
+            .wrap_err_with(|| format!("Couldn't write to page {path:?}"))?;
+        self.cache.record(path, inputs);
 
-    Ok(())
-}
+        Ok(())
+    }
 
-// TODO: Basically duplicated from blog
-fn generate_media_log(output: &Path) -> eyre::Result<()> {
-    let media_path = PathBuf::from(
-        std::env::var("ODILF_MEDIA_LOG_PATH")
-            .wrap_err("Couldn't get `ODILF_MEDIA_LOG_PATH` env variable.")?,
-    );
+    fn save_page_no_shell(
+        &self,
+        path: impl AsRef<Path>,
+        inputs: &impl Serialize,
+        render: impl FnOnce() -> eyre::Result<Markup>,
+    ) -> eyre::Result<()> {
+        self.save_file(path, inputs, || Ok(render()?.0))
+    }
 
-    let media_output = output.join("media-log");
-    fs::create_dir_all(&media_output)
-        .wrap_err_with(|| format!("Couldn't create media log path at {media_output:?}"))?;
+    /// Like [`Site::save_file`], but for HTML pages wrapped in the [`shell`].
+    ///
+    /// The page is registered for the sitemap (unless it's unlisted), with the
+    /// date of `meta` as its last modification, which fails if another page
+    /// has the same `path`.
+    fn save_page(
+        &self,
+        path: impl AsRef<Path>,
+        mut meta: PageMeta,
+        inputs: &impl Serialize,
+        render: impl FnOnce() -> Markup,
+    ) -> eyre::Result<()> {
+        let path = path.as_ref();
+        self.pages.register(path, meta.date, !meta.unlisted)?;
+        meta.canonical = Some(self.config.page_url(path));
+        meta.math = meta.math && self.math_assets();
+
+        self.save_page_no_shell(path, &(inputs, &meta), || {
+            Ok(shell(self.config, &meta, render()))
+        })
+    }
+}
 
-    tracing::info!(?media_output, ?media_path);
+/// Logs all the errors in `results`, returning an error if there was any.
+fn report_errors(what: &str, results: Vec<eyre::Result<()>>) -> eyre::Result<()> {
+    let mut errors = results
+        .into_iter()
+        .filter_map(Result::err)
+        .collect::<Vec<_>>();
 
-    let mut media_entries = fs::read_dir(&media_path)?
-        .map(|entry| {
-            let entry = entry?;
-            let mut path = entry.path();
-            if path.is_dir() {
-                tracing::debug!("Skipping directory");
-                return eyre::Ok(None);
+    match errors.len() {
+        0 => Ok(()),
+        1 => Err(errors.remove(0)),
+        count => {
+            for err in errors {
+                tracing::error!(?err);
             }
+            eyre::bail!("{count} {what} failed, see the errors above")
+        }
+    }
+}
 
-            if path.extension().and_then(|ext| ext.to_str()) != Some("md") {
-                tracing::debug!(entry=?entry.path().file_name(), "Skipping non `.md` file");
-                return Ok(None);
-            }
+/// Paths of the markdown files in `dir`, sorted so that the output is deterministic.
+fn markdown_files(dir: &Path) -> eyre::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir).wrap_err_with(|| format!("Couldn't read {dir:?}"))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            tracing::debug!(?path, "Skipping directory");
+            continue;
+        }
 
-            let post_content = fs::read_to_string(&path).wrap_err("Couldn't read media log")?;
+        if path.extension().and_then(|ext| ext.to_str()) != Some("md") {
+            tracing::debug!(?path, "Skipping non `.md` file");
+            continue;
+        }
+
+        paths.push(path);
+    }
+
+    paths.sort();
+    Ok(paths)
+}
 
-            path.set_extension("");
+/// Generates the entry of each of `paths` in parallel, returning the
+/// successful ones in the same order.
+///
+/// Entries that fail are skipped, and their errors are added to `errors`.
+fn generate_entries<T: Send>(
+    errors: &ErrorReport,
+    what: &str,
+    paths: &[PathBuf],
+    generate: impl Fn(&Path, &str) -> eyre::Result<Option<T>> + Sync,
+) -> Vec<T> {
+    let results = paths
+        .par_iter()
+        .map(|path| {
+            let _span = tracing::warn_span!("entry", file = %path.display()).entered();
             let slug = path
-                .file_name()
+                .file_stem()
                 .and_then(|name| name.to_str())
                 .wrap_err("Couldn't get file name")?;
 
-            tracing::info!(?slug, "Generating media log page");
-            let entry = MediaLog::from_slug_and_content(slug, &post_content)
-                .wrap_err_with(|| format!("Couldn't form media log of {slug}"))?;
+            generate(path, slug).wrap_err_with(|| format!("Couldn't generate {what}"))
+        })
+        .collect::<Vec<_>>();
+
+    let mut entries = Vec::with_capacity(results.len());
+    for (path, result) in paths.iter().zip(results) {
+        match result {
+            Ok(Some(entry)) => entries.push(entry),
+            Ok(None) => (),
+            Err(err) => errors.push(path, err),
+        }
+    }
+
+    entries
+}
 
-            // TODO: This shouldn't need to allocate
-            save_page(
-                format!("media-log/{}/index.html", entry.slug),
-                entry.render(),
-                output,
+/// Generates the pages of every entry of the collection `T`, its index and its
+/// extra outputs.
+fn generate_collection<T: Collection>(site: &Site) -> eyre::Result<()> {
+    let config = site.config;
+    let source = T::source_dir(config);
+    let collection_output = config.output.join(T::PATH);
+    fs::create_dir_all(&collection_output)
+        .wrap_err_with(|| format!("Couldn't create output directory at {collection_output:?}"))?;
+
+    tracing::info!(?collection_output, ?source);
+
+    let paths = markdown_files(source)?;
+    let mut entries = generate_entries(&site.errors, T::NAME, &paths, |path, slug| {
+        tracing::debug!(?path, "Reading {}", T::NAME);
+        let content = fs::read_to_string(path).wrap_err("Couldn't read file")?;
+
+        let inputs = Hash::of(&(&content, T::cache_inputs(&content, config)));
+        let entry = site
+            .cache
+            .entry(&format!("{}/{slug}", T::PATH), inputs, || {
+                T::parse(slug, &content, config)
+            })
+            .wrap_err_with(|| format!("Couldn't parse {} {slug:?}", T::NAME))?;
+        let Some(entry) = entry else {
+            return Ok(None);
+        };
+
+        if entry.is_stale() {
+            site.mark_stale(format!("{} {slug:?}", T::NAME));
+        }
+        for warning in entry.warnings() {
+            tracing::warn!("{warning}");
+        }
+
+        Ok(Some(entry))
+    });
+    entries.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+
+    // Entries that conflict with others are reported and skipped, like the
+    // ones that fail to parse.
+    let mut conflicting = Vec::new();
+    for (i, err) in T::conflicts(&entries) {
+        let slug = entries[i].slug().to_string();
+        let path = paths
+            .iter()
+            .find(|path| path.file_stem().is_some_and(|stem| *stem == *slug))
+            .expect("Entries come from the paths");
+        site.errors
+            .push(path, err.wrap_err(format!("Couldn't generate {}", T::NAME)));
+        conflicting.push(slug);
+    }
+    entries.retain(|entry| !conflicting.iter().any(|slug| slug == entry.slug()));
+
+    // Pages link to other entries, so they're generated once all are parsed.
+    let neighbours = collection::neighbours(&entries);
+    let results = entries
+        .par_iter()
+        .zip(&neighbours)
+        .map(|(entry, neighbours)| {
+            let slug = entry.slug();
+            tracing::info!(?slug, "Generating {} page", T::NAME);
+            let meta = PageMeta {
+                unlisted: !(entry.is_listed() && entry.is_public()),
+                ..entry.meta()
+            };
+            site.save_page(
+                format!("{}/{slug}/index.html", T::PATH),
+                meta,
+                &(entry, neighbours),
+                || entry.render_page(neighbours),
             )?;
 
-            Ok(Some(entry))
-        })
-        .flat_map(|result| match result {
-            Ok(Some(entry)) => Some(entry),
-            Ok(None) => None,
-            Err(err) => {
-                tracing::error!(?err);
-                None
+            for output in T::ENTRY_OUTPUTS {
+                site.save_file(
+                    format!("{}/{slug}/{}", T::PATH, output.file_name),
+                    &(output.inputs)(entry),
+                    || (output.render)(entry),
+                )?;
             }
+
+            Ok(())
         })
         .collect::<Vec<_>>();
+    report_errors(&format!("{} pages", T::NAME), results)?;
 
-    media_entries.sort_by_key(|media_log| Reverse(media_log.date));
-    save_page(
-        "media-log/index.html",
-        media::home(media_entries.iter()),
-        output,
-    )?;
+    // Unlisted entries have their page, but aren't linked from anywhere.
+    entries.retain(T::is_listed);
+    for document in entries
+        .iter()
+        .filter(|entry| entry.is_public())
+        .filter_map(T::search_document)
+    {
+        site.search.register(document);
+    }
+    let meta = PageMeta {
+        date: entries
+            .iter()
+            .filter(|entry| entry.is_public())
+            .filter_map(T::date)
+            .max(),
+        ..T::index_meta()
+    };
+    site.save_page(format!("{}/index.html", T::PATH), meta, &entries, || {
+        T::render_index(config, &entries)
+    })?;
+
+    for output in T::EXTRA_OUTPUTS {
+        site.save_file(output.path, &entries, || (output.render)(config, &entries))?;
+    }
+
+    for output in T::derived_outputs(config, &entries)? {
+        match output {
+            DerivedOutput::Page {
+                path,
+                meta,
+                content,
+            } => site.save_page(path, meta, &Hash::of(&content.0), || content)?,
+            DerivedOutput::File { path, content } => {
+                site.save_file(path, &Hash::of(&content), || Ok(content))?;
+            }
+        }
+    }
+
+    for asset in entries.iter().flat_map(T::assets) {
+        let src = source.join(asset);
+        let dst = collection_output.join(asset);
+        if let Some(parent) = dst.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(src, dst).wrap_err_with(|| format!("Couldn't copy referenced file {asset:?}"))?;
+    }
 
-    fs::create_dir_all(output.join("static"))?;
-    fs::write(
-        output.join("static/media-log.json"),
-        serde_json::to_string_pretty(&media_entries).wrap_err("Can't serialize media-log JSON")?,
-    )?;
     Ok(())
 }
 
-fn generate_pics(output: &Path) -> eyre::Result<()> {
-    let immich_url = std::env::var("IMMICH_URL")
-        .wrap_err("Couldn't get `IMMICH_URL` env variable. Set it to your Immich server URL (e.g., https://immich.example.com).")?;
-    let album_id = std::env::var("IMMICH_ALBUM_ID")
-        .wrap_err("Couldn't get `IMMICH_ALBUM_ID` env variable. Set it to your album ID.")?;
-    let api_key = std::env::var("IMMICH_API_KEY")
-        .wrap_err("Couldn't get `IMMICH_API_KEY` env variable. Set it to your Immich API key.")?;
+fn generate_projects(site: &Site) -> eyre::Result<()> {
+    let src = &site.config.projects.path;
 
-    tracing::info!("Fetching photos from Immich album");
-    let mut photos =
-        pics::immich::fetch::fetch_immich_album(&immich_url, &album_id, &api_key, output)?;
+    let project_data: Projects = toml::from_str(&fs::read_to_string(src)?)
+        .wrap_err_with(|| format!("Couldn't read projects from {src:?}"))?;
+
+    let meta = PageMeta {
+        date: project_data
+            .iter()
+            .map(|(_, project)| project.last_update.to_zoned(TimeZone::UTC).date())
+            .max(),
+        ..PageMeta::titled("projects", projects::DESC)
+    };
+    site.save_page("projects/index.html", meta, &project_data, || {
+        projects::home(&project_data)
+    })?;
+
+    Ok(())
+}
+
+fn generate_pics(site: &Site) -> eyre::Result<()> {
+    let config = site.config;
+    let pics = &config.pics;
+
+    let mut photos = if config.build.offline {
+        tracing::info!("Offline build, using cached photos from Immich album");
+        let album = match &pics.album_id {
+            Some(album_id) => pics::immich::fetch::cached_immich_album(album_id, &config.output)?,
+            None => None,
+        };
+        match album {
+            Some(album) => {
+                for photo in album.missing {
+                    tracing::warn!(?photo.id, "Photo not cached, skipping");
+                    site.mark_stale(format!("photo {:?}", photo.id));
+                }
+                album.photos
+            }
+            None => {
+                tracing::warn!("Immich album was never fetched, skipping all photos");
+                site.mark_stale("Immich album".to_string());
+                Vec::new()
+            }
+        }
+    } else {
+        tracing::info!("Fetching photos from Immich album");
+        let (immich_url, album_id, api_key) = pics.immich()?;
+        pics::immich::fetch::fetch_immich_album(
+            // Avoid double slashes when joining API paths.
+            immich_url.as_str().trim_end_matches('/'),
+            album_id,
+            api_key,
+            &config.output,
+            config.build.release,
+        )?
+    };
 
     // Move last pic to last
     if let Some(pos) = photos
@@ -250,17 +850,25 @@ fn generate_pics(output: &Path) -> eyre::Result<()> {
     }
 
     let all_ids = photos.iter().map(|p| p.id.clone()).collect::<Vec<_>>();
-    for (index, photo) in photos.iter().enumerate() {
-        let page = pics::pic(photo, index, &all_ids);
-        save_page(format!("pics/{}/index.html", photo.id), page, output)?;
-    }
+    let results = photos
+        .par_iter()
+        .enumerate()
+        .map(|(index, photo)| {
+            site.save_page(
+                format!("pics/{}/index.html", photo.id),
+                pics::meta(photo),
+                &(photo, index, &all_ids),
+                || pics::pic(photo, index, &all_ids),
+            )
+        })
+        .collect::<Vec<_>>();
+    report_errors("photo pages", results)?;
 
     tracing::info!("Generated {} photo pages", photos.len());
-    save_page(
-        "pics/index.html",
-        pics::home(photos.iter(), &all_ids),
-        output,
-    )?;
+    let meta = PageMeta::titled("pics", pics::DESC);
+    site.save_page("pics/index.html", meta, &photos, || {
+        pics::home(photos.iter(), &all_ids)
+    })?;
 
     Ok(())
 }
@@ -317,3 +925,12 @@ fn copy_favicon(output: &Path) -> eyre::Result<()> {
     fs::copy("public/logo.png", output.join("favicon.png"))?;
     Ok(())
 }
+
+fn copy_placeholder_cover(output: &Path) -> eyre::Result<()> {
+    fs::create_dir_all(output.join("static"))?;
+    fs::copy(
+        "public/placeholder-cover.svg",
+        output.join("static/placeholder-cover.svg"),
+    )?;
+    Ok(())
+}
@@ -0,0 +1,121 @@
+//! Self-hosted stylesheet and fonts of KaTeX, for the pages with math.
+//!
+//! They're downloaded once from the release of KaTeX that renders the math
+//! into the cache directory of the site, and copied to the output directory in
+//! each build with math, so that readers don't request anything from a third
+//! party. Offline builds only use the cache. If it's empty, or the download
+//! fails, the pages don't link the stylesheet at all (the math is still
+//! readable, just unstyled).
+
+use color_eyre::eyre::{self, Context as _};
+use rayon::prelude::*;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::config::SiteConfig;
+
+/// Version of KaTeX bundled in the `katex` crate, whose output should match
+/// the stylesheet.
+pub const VERSION: &str = "0.16.4";
+
+/// Directory of the files, relative to the output directory.
+pub const DIR: &str = "static/katex";
+
+/// Path of the stylesheet, from the root of the site.
+pub const STYLESHEET_HREF: &str = "/static/katex/katex.min.css";
+
+const STYLESHEET: &str = "katex.min.css";
+
+fn cache_dir(config: &SiteConfig) -> PathBuf {
+    config.cache_dir.join("katex").join(VERSION)
+}
+
+/// Makes sure that the stylesheet and fonts are cached, downloading them if
+/// they aren't.
+///
+/// Returns `false` if they aren't cached in an offline build, in which case
+/// they can't be copied or linked.
+pub fn fetch_assets(config: &SiteConfig) -> eyre::Result<bool> {
+    let cache = cache_dir(config);
+    if cache.join(STYLESHEET).exists() {
+        return Ok(true);
+    }
+    if config.build.offline {
+        return Ok(false);
+    }
+
+    download(&cache).wrap_err("Couldn't download KaTeX stylesheet and fonts")?;
+    Ok(true)
+}
+
+/// Copies the cached stylesheet and fonts (see [`fetch_assets`]) to the output
+/// directory.
+pub fn copy_assets(config: &SiteConfig) -> eyre::Result<()> {
+    let cache = cache_dir(config);
+    let destination = config.output.join(DIR);
+    for file in files(&fs::read_to_string(cache.join(STYLESHEET))?)
+        .into_iter()
+        .chain([STYLESHEET.to_string()])
+    {
+        let path = destination.join(&file);
+        fs::create_dir_all(path.parent().expect("Files are in the destination"))?;
+        fs::copy(cache.join(&file), &path).wrap_err_with(|| format!("Couldn't copy {file:?}"))?;
+    }
+
+    Ok(())
+}
+
+/// Downloads the stylesheet and the fonts it uses to `cache`.
+fn download(cache: &Path) -> eyre::Result<()> {
+    let base = format!("https://cdn.jsdelivr.net/npm/katex@{VERSION}/dist");
+    tracing::info!("Downloading KaTeX stylesheet and fonts from {base}");
+
+    let client = reqwest::blocking::Client::new();
+    let get = |file: &str| -> eyre::Result<Vec<u8>> {
+        let url = format!("{base}/{file}");
+        let response = client
+            .get(&url)
+            .send()
+            .and_then(reqwest::blocking::Response::error_for_status)
+            .wrap_err_with(|| format!("Couldn't fetch {url}"))?;
+        Ok(response.bytes()?.to_vec())
+    };
+
+    let stylesheet = String::from_utf8(get(STYLESHEET)?).wrap_err("Stylesheet isn't UTF-8")?;
+
+    // Downloaded to a separate directory first, so that interrupted downloads
+    // don't leave an incomplete cache.
+    let partial = cache.with_extension("partial");
+    files(&stylesheet).par_iter().try_for_each(|file| {
+        let path = partial.join(file);
+        fs::create_dir_all(path.parent().expect("Files are in the cache"))?;
+        fs::write(path, get(file)?)?;
+        eyre::Ok(())
+    })?;
+    fs::write(partial.join(STYLESHEET), stylesheet)?;
+
+    if cache.exists() {
+        fs::remove_dir_all(cache)?;
+    }
+    fs::rename(&partial, cache)?;
+    Ok(())
+}
+
+/// Files referenced by `stylesheet` (the fonts), relative to it.
+fn files(stylesheet: &str) -> Vec<String> {
+    let mut files = stylesheet
+        .split("url(")
+        .skip(1)
+        .filter_map(|rest| {
+            let file = rest[..rest.find(')')?].trim_matches(['"', '\'']);
+            // Only relative paths inside the directory, never anything remote.
+            let is_local = !file.contains(':') && !file.starts_with('/') && !file.contains("..");
+            is_local.then(|| file.to_string())
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    files.dedup();
+    files
+}
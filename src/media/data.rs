@@ -1,11 +1,23 @@
 use color_eyre::eyre::{self, ContextCompat as _};
 use reqwest::header::USER_AGENT;
-use std::{fs, path::PathBuf};
+use std::{fs, path::Path};
 use url::Url;
 
-pub fn get_image<'a>(urls: impl Iterator<Item = &'a Url>, slug: &str) -> eyre::Result<String> {
-    let cache_file_path =
-        PathBuf::from(format!("target/debug/site/media-log/covers-cache/{}", slug));
+/// Gets the URL of the cover image from the first supported link in `urls`.
+///
+/// The result is cached in `cache_dir`. If `offline` is set, only the cache is
+/// used, returning `None` when the image isn't there.
+pub fn get_image<'a>(
+    urls: impl Iterator<Item = &'a Url>,
+    slug: &str,
+    cache_dir: &Path,
+    offline: bool,
+) -> eyre::Result<Option<String>> {
+    let cache_file_path = cache_dir.join(slug);
+    if offline {
+        return Ok(fs::read_to_string(&cache_file_path).ok());
+    }
+
     let image_url = fs::read_to_string(&cache_file_path).or_else(|_| {
         for url in urls {
             tracing::debug!(?url);
@@ -27,7 +39,7 @@ pub fn get_image<'a>(urls: impl Iterator<Item = &'a Url>, slug: &str) -> eyre::R
         eyre::bail!("No image found")
     })?;
 
-    Ok(image_url)
+    Ok(Some(image_url))
 }
 
 fn extract_wikipedia_image(url: &Url) -> eyre::Result<String> {
@@ -1,19 +1,14 @@
-use color_eyre::eyre::{self, Context as _, ContextCompat as _};
-use gray_matter::{Matter, engine::YAML};
+use color_eyre::eyre;
 
-use crate::media::MediaLog;
+use crate::{front_matter, media::MediaLog};
 
-pub fn parse_media_log(content: &str) -> eyre::Result<MediaLog<Option<String>>> {
-    let frontmatter_parser = Matter::<YAML>::new();
-
-    let media_log = frontmatter_parser
-        .parse::<MediaLog<Option<String>>>(content)
-        .wrap_err("Couldn't parse frontmatter")?;
-
-    let media_log = media_log.data.wrap_err("Frontmatter not found")?;
+/// The front matter of a media log entry, with warnings about its unknown
+/// fields.
+pub fn parse_media_log(content: &str) -> eyre::Result<(MediaLog<Option<String>>, Vec<String>)> {
+    let (media_log, warnings): (MediaLog<Option<String>>, _) = front_matter::parse(content)?;
     if media_log.review.is_some() {
         eyre::bail!("`review` field present in frontmatter");
     }
 
-    Ok(media_log)
+    Ok((media_log, warnings))
 }
@@ -1,10 +1,21 @@
-use crate::components::back;
+use crate::{
+    PageMeta,
+    collection::{Collection, EntryLink, ExtraOutput, Neighbours},
+    components::back,
+    config::SiteConfig,
+    front_matter::{Field, FrontMatter},
+    search,
+};
 use color_eyre::eyre::{self, Context};
 use comrak::{ExtensionOptions, Options, RenderOptions};
 use jiff::civil::Date as JiffDate;
 use maud::{Markup, PreEscaped, Render, html};
 use serde::{Deserialize, Serialize};
-use std::fmt::{self, Write};
+use std::{
+    cmp::Reverse,
+    fmt::{self, Write},
+    path::Path,
+};
 use url::Url;
 
 mod data;
@@ -12,12 +23,16 @@ mod markdown;
 
 pub const DESC: &str = "logging and reviews of books, movies and videogames.";
 
+/// Cover used for entries whose image can't be fetched in offline builds.
+pub const PLACEHOLDER_COVER: &str = "/static/placeholder-cover.svg";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct MediaLog<ImageUrl = String> {
     pub title: String,
-    // Slug is set after parsing.
-    #[serde(skip)]
+    // Slug is set after parsing, it's not in the front matter. It's only
+    // serialized for the build cache, see `PublicEntry`.
+    #[serde(default)]
     pub slug: String,
     #[serde(rename = "type")]
     pub typ: MediaType,
@@ -26,6 +41,24 @@ pub struct MediaLog<ImageUrl = String> {
     pub urls: Vec<Url>,
     pub review: Option<String>,
     pub image_url: ImageUrl,
+    /// Problems of the entry that don't stop it from being built. Like the
+    /// slug, it's not in the front matter nor the public JSON.
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+impl FrontMatter for MediaLog<Option<String>> {
+    const FIELDS: &'static [Field] = &[
+        Field::required::<String>("title", "a string"),
+        Field::required::<MediaType>("type", "one of book, movie, videogame or music"),
+        Field::required::<Rating>("rating", "a number from 0 to 5"),
+        Field::required::<Date>(
+            "date",
+            "a date like 2024-01-31, or a range like [2024-01-01, 2024-01-31]",
+        ),
+        Field::required::<Vec<Url>>("urls", "a list of URLs"),
+        Field::optional::<String>("image-url", "a URL"),
+    ];
 }
 
 pub fn home<'a>(entries: impl Iterator<Item = &'a MediaLog>) -> Markup {
@@ -101,9 +134,134 @@ impl Render for MediaLog {
     }
 }
 
+/// An entry in the public JSON of the media log, with the fields of its front
+/// matter (so without the slug), in the same order.
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct PublicEntry<'a> {
+    title: &'a str,
+    #[serde(rename = "type")]
+    typ: &'a MediaType,
+    rating: &'a Rating,
+    date: &'a Date,
+    urls: &'a [Url],
+    review: &'a Option<String>,
+    image_url: &'a str,
+}
+
+fn media_log_json(entries: &[MediaLog]) -> serde_json::Result<String> {
+    let entries = entries
+        .iter()
+        .map(|entry| PublicEntry {
+            title: &entry.title,
+            typ: &entry.typ,
+            rating: &entry.rating,
+            date: &entry.date,
+            urls: &entry.urls,
+            review: &entry.review,
+            image_url: &entry.image_url,
+        })
+        .collect::<Vec<_>>();
+
+    serde_json::to_string_pretty(&entries)
+}
+
+impl Collection for MediaLog {
+    const NAME: &'static str = "media log entry";
+    const PATH: &'static str = "media-log";
+    const EXTRA_OUTPUTS: &'static [ExtraOutput<Self>] = &[ExtraOutput {
+        path: "static/media-log.json",
+        render: |_, entries| media_log_json(entries).wrap_err("Can't serialize media-log JSON"),
+    }];
+
+    fn source_dir(config: &SiteConfig) -> &Path {
+        &config.media_log.path
+    }
+
+    fn parse(slug: &str, content: &str, config: &SiteConfig) -> eyre::Result<Option<Self>> {
+        Self::from_slug_and_content(slug, content, config).map(Some)
+    }
+
+    fn check(_slug: &str, content: &str, _config: &SiteConfig) -> eyre::Result<Vec<String>> {
+        // Only the front matter, to not fetch the covers.
+        markdown::parse_media_log(content).map(|(_, warnings)| warnings)
+    }
+
+    fn slug(&self) -> &str {
+        &self.slug
+    }
+
+    fn link(&self) -> EntryLink {
+        EntryLink {
+            href: format!("/media-log/{}", self.slug),
+            title: self.title.clone(),
+            date: self.date(),
+        }
+    }
+
+    fn date(&self) -> Option<JiffDate> {
+        Some(self.date.last())
+    }
+
+    fn sort_key(&self) -> impl Ord {
+        (Reverse(self.date), &self.slug)
+    }
+
+    fn is_stale(&self) -> bool {
+        self.image_url == PLACEHOLDER_COVER
+    }
+
+    fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    fn search_document(&self) -> Option<search::Document> {
+        let review = self.review.clone().unwrap_or_default();
+        Some(search::Document {
+            url: format!("/media-log/{}", self.slug),
+            title: self.title.clone(),
+            kind: self.typ.to_string(),
+            date: self.date(),
+            summary: search::text(&review),
+            keywords: vec![self.typ.to_string()],
+            body: review,
+        })
+    }
+
+    fn meta(&self) -> PageMeta {
+        PageMeta {
+            title: Some(self.title.clone()),
+            description: Some(format!(
+                "Review of the {} {}, rated {}/5.",
+                self.typ, self.title, self.rating.0
+            )),
+            image: Some(self.image_url.clone()),
+            date: self.date(),
+            article: true,
+            ..Default::default()
+        }
+    }
+
+    fn render_page(&self, _neighbours: &Neighbours) -> Markup {
+        self.render()
+    }
+
+    fn index_meta() -> PageMeta {
+        PageMeta::titled("media log", DESC)
+    }
+
+    fn render_index(_config: &SiteConfig, entries: &[Self]) -> Markup {
+        home(entries.iter())
+    }
+}
+
 impl MediaLog {
-    pub fn from_slug_and_content(slug: impl Into<String>, content: &str) -> eyre::Result<Self> {
-        let mut log = markdown::parse_media_log(content).wrap_err("Invalid frontmatter")?;
+    pub fn from_slug_and_content(
+        slug: impl Into<String>,
+        content: &str,
+        config: &SiteConfig,
+    ) -> eyre::Result<Self> {
+        let (mut log, warnings) = markdown::parse_media_log(content)?;
 
         // From blog again, might be unecessary.
         // TODO: Factor out common configuration.
@@ -125,7 +283,16 @@ impl MediaLog {
         log.review = (!content.is_empty()).then(|| comrak::markdown_to_html(content, &options));
         log.slug = slug.into();
         let image_url = match log.image_url {
-            None => data::get_image(log.urls.iter(), &log.slug)?,
+            None => data::get_image(
+                log.urls.iter(),
+                &log.slug,
+                &config.output.join("media-log/covers-cache"),
+                config.build.offline,
+            )?
+            .unwrap_or_else(|| {
+                tracing::warn!(slug = log.slug, "Cover not cached, using placeholder");
+                PLACEHOLDER_COVER.to_string()
+            }),
             Some(url) => url,
         };
 
@@ -138,6 +305,7 @@ impl MediaLog {
             urls: log.urls,
             review: log.review,
             image_url,
+            warnings,
         })
     }
 
@@ -203,12 +371,42 @@ impl fmt::Display for MediaType {
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 #[serde(untagged)]
+#[serde(try_from = "RawDate")]
 pub enum Date {
     Single(JiffDate),
     Range(JiffDate, JiffDate),
 }
 
+/// [`Date`] before checking that ranges are in order.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawDate {
+    Single(JiffDate),
+    Range(JiffDate, JiffDate),
+}
+
+impl TryFrom<RawDate> for Date {
+    type Error = String;
+
+    fn try_from(date: RawDate) -> Result<Self, Self::Error> {
+        match date {
+            RawDate::Single(date) => Ok(Self::Single(date)),
+            RawDate::Range(start, end) if start <= end => Ok(Self::Range(start, end)),
+            RawDate::Range(start, end) => {
+                Err(format!("range ends at {end}, before its start {start}"))
+            }
+        }
+    }
+}
+
 impl Date {
+    /// The day it was finished, for ranges.
+    pub fn last(&self) -> JiffDate {
+        match self {
+            Self::Single(date) | Self::Range(_, date) => *date,
+        }
+    }
+
     pub fn representative(&self) -> JiffDate {
         match self {
             Self::Single(date) => *date,
@@ -255,8 +453,21 @@ impl fmt::Display for Date {
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(try_from = "f32")]
 pub struct Rating(f32);
 
+impl TryFrom<f32> for Rating {
+    type Error = String;
+
+    fn try_from(rating: f32) -> Result<Self, Self::Error> {
+        if (Self::MIN.0..=Self::MAX.0).contains(&rating) {
+            Ok(Self(rating))
+        } else {
+            Err(format!("rating {rating} is out of range"))
+        }
+    }
+}
+
 impl Rating {
     pub const MIN: Self = Rating(0.0);
     pub const MAX: Self = Rating(5.0);
@@ -3,13 +3,16 @@
 //! There is a "cache" file that holds all photo metadata. This is fetched if
 //! - the `.immich_cache` file is missing or
 //! - if some pic that is listed in the cache file is missing
-//! - or if it compiled in release mode.
+//! - or if `refresh` is set (for release builds).
+//!
+//! Other builds use the cache whenever it's complete.
 
 // NOTE: This file is badly coded. There are a thousand invisible invariants
 // not properly upheld. It just does not seem worth to improve.
 
 use color_eyre::eyre::{self, Context as _, ContextCompat as _};
 use image::ImageReader;
+use rayon::prelude::*;
 use reqwest::header::USER_AGENT;
 use std::fs;
 use std::io::Write as _;
@@ -46,26 +49,50 @@ fn load_from_cache(album_id: &str) -> eyre::Result<Option<Vec<Photo>>> {
     Ok(Some(photos))
 }
 
+/// Photos of an album that are available without accessing the network.
+#[derive(Debug, Default)]
+pub struct CachedAlbum {
+    /// Photos with their images (and thumbnails) in the output directory.
+    pub photos: Vec<Photo>,
+    /// Photos in the cached metadata whose images are missing.
+    pub missing: Vec<Photo>,
+}
+
+/// Gets the photos of an Immich album only from the cache, for offline builds.
+///
+/// Returns `None` if the metadata of the album was never cached.
+pub fn cached_immich_album(album_id: &str, output_dir: &Path) -> eyre::Result<Option<CachedAlbum>> {
+    let Some(cached_photos) = load_from_cache(album_id)? else {
+        return Ok(None);
+    };
+
+    let (photos, missing) = cached_photos.into_iter().partition(|photo| {
+        photo.fs_path(output_dir).exists() && photo.fs_thumb_path(output_dir).exists()
+    });
+
+    Ok(Some(CachedAlbum { photos, missing }))
+}
+
 /// Fetch photos from an Immich album, downloading and converting images
+///
+/// If `refresh` is not set, the cached metadata is used when available.
 pub fn fetch_immich_album(
     immich_url: &str,
     album_id: &str,
     api_key: &str,
     output_dir: &Path,
+    refresh: bool,
 ) -> eyre::Result<Vec<Photo>> {
     tracing::info!("Fetching Immich album: {}", album_id);
 
     let images_dir = output_dir.join("static/pics");
     fs::create_dir_all(&images_dir).wrap_err("Failed to create images directory")?;
 
-    if let Some(cached_photos) = load_from_cache(album_id)?
-        && !cfg!(debug_assertions)
-    {
+    if !refresh && let Some(cached_photos) = load_from_cache(album_id)? {
         tracing::info!("Found cached photo metadata, verifying image files...");
 
         let all_files_exist = cached_photos.iter().all(|photo| {
-            let full_path = images_dir.join(&photo.id);
-            full_path.exists()
+            photo.fs_path(output_dir).exists() && photo.fs_thumb_path(output_dir).exists()
         });
 
         if all_files_exist {
@@ -105,9 +132,10 @@ pub fn fetch_immich_album(
         .json()
         .wrap_err("Failed to parse Immich API response as JSON")?;
 
+    // Download and convert in parallel, since it's by far the slowest part.
     let photos = album
         .assets
-        .into_iter()
+        .into_par_iter()
         .map(|asset| get_immich_pic(asset, output_dir, immich_url, api_key))
         .collect::<eyre::Result<Vec<_>>>()?;
 
@@ -4,6 +4,7 @@ use maud::Markup;
 use maud::PreEscaped;
 use maud::html;
 
+use crate::PageMeta;
 use crate::components::back;
 use immich::Photo;
 
@@ -40,6 +41,17 @@ pub fn home<'a>(photos: impl Iterator<Item = &'a Photo>, all_ids: &[String]) ->
     }
 }
 
+/// Metadata of the page of `photo`.
+pub fn meta(photo: &Photo) -> PageMeta {
+    let caption = (!photo.caption.is_empty()).then(|| photo.caption.clone());
+    PageMeta {
+        title: Some(caption.clone().unwrap_or_else(|| "pic".to_string())),
+        description: caption,
+        image: Some(photo.path()),
+        ..Default::default()
+    }
+}
+
 pub fn pic(photo: &Photo, index: usize, all_ids: &[String]) -> Markup {
     let id = &photo.id;
     let prev_id = (index > 0).then(|| all_ids[index - 1].as_str());
@@ -9,7 +9,7 @@ use crate::components::{self, tag};
 
 pub const DESC: &str = "most of my personal coding projects.";
 
-pub fn home(projects: Projects) -> Markup {
+pub fn home(projects: &Projects) -> Markup {
     let link = |url, arrow, name| {
         html! {
             a."text-inherit group/link transition-[translate] -translate-x-[1.5ch] hover:-translate-x-[1ch] w-full inline-block"
@@ -0,0 +1,115 @@
+//! Reporting of errors in the content files.
+//!
+//! Instead of stopping at the first broken entry, every failure is collected in
+//! an [`ErrorReport`] and shown at the end of the build, with the file, the
+//! line (when it's known) and the chain of causes of each error.
+
+use color_eyre::eyre;
+use std::{fmt, path::PathBuf, sync::Mutex};
+
+/// An error at a specific line of a file.
+///
+/// Parsers use this as the root cause of their errors, so that the report can
+/// point to the line.
+#[derive(Debug)]
+pub struct LineError {
+    /// Line in the file, starting at 1.
+    pub line: usize,
+    /// Column in the line, starting at 1, if it's known.
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for LineError {}
+
+/// An error in a content file.
+#[derive(Debug)]
+pub struct ContentError {
+    pub file: PathBuf,
+    pub error: eyre::Report,
+}
+
+impl ContentError {
+    /// Where in the file the error happened, if it's known.
+    pub fn location(&self) -> Option<&LineError> {
+        self.error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<LineError>())
+    }
+
+    /// The line of the file where the error happened, if it's known.
+    pub fn line(&self) -> Option<usize> {
+        self.location().map(|error| error.line)
+    }
+}
+
+impl fmt::Display for ContentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let Some(location) = self.location() {
+            write!(f, ":{}", location.line)?;
+            if let Some(column) = location.column {
+                write!(f, ":{column}")?;
+            }
+        }
+
+        for (i, cause) in self.error.chain().enumerate() {
+            write!(f, "\n  {i}: {cause}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The errors of all the content files in a build.
+///
+/// Can be shared between threads, since entries are generated in parallel.
+#[derive(Debug, Default)]
+pub struct ErrorReport {
+    errors: Mutex<Vec<ContentError>>,
+}
+
+impl ErrorReport {
+    pub fn push(&self, file: impl Into<PathBuf>, error: eyre::Report) {
+        self.errors().push(ContentError {
+            file: file.into(),
+            error,
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.errors().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn errors(&self) -> std::sync::MutexGuard<'_, Vec<ContentError>> {
+        self.errors
+            .lock()
+            .expect("Error report lock shouldn't be poisoned")
+    }
+}
+
+impl fmt::Display for ErrorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut errors = self.errors();
+        errors.sort_by(|a, b| (&a.file, a.line()).cmp(&(&b.file, b.line())));
+
+        for (i, error) in errors.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{error}")?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,263 @@
+//! Client-side search of the blog and the media log.
+//!
+//! Entries register a [`Document`] as they are generated, and at the end of
+//! the build every document is tokenized into a prebuilt [`SearchIndex`],
+//! which is written as JSON. The `/search` page fetches it and matches the
+//! query with a small script (with prefix and typo tolerance), so the site
+//! stays fully static.
+
+use jiff::civil::Date;
+use maud::{Markup, PreEscaped, html};
+use scraper::{Html, Node};
+use serde::Serialize;
+use std::{collections::BTreeMap, sync::Mutex};
+
+use crate::components::back;
+
+pub const DESC: &str = "search through the blog and the media log.";
+
+/// Path of the index, relative to the output directory.
+pub const INDEX_PATH: &str = "static/search-index.json";
+
+/// Weight of a term in each part of a document, so that matches in the title
+/// rank above matches in the body.
+const TITLE_WEIGHT: u32 = 10;
+const KEYWORD_WEIGHT: u32 = 5;
+const SUMMARY_WEIGHT: u32 = 3;
+/// Each appearance in the body counts once, up to this many.
+const MAX_BODY_WEIGHT: u32 = 5;
+
+/// Shortest term in the index (and in the queries), in characters.
+pub const MIN_TERM_LENGTH: usize = 2;
+
+/// Characters of the summaries in the index.
+const MAX_SUMMARY_LENGTH: usize = 200;
+
+/// Something that can be found with the search.
+#[derive(Debug, Clone, Serialize)]
+pub struct Document {
+    /// Path of the page, from the root of the site.
+    pub url: String,
+    pub title: String,
+    /// What it is (e.g. "blog post" or "book"), shown in the results.
+    pub kind: String,
+    pub date: Option<Date>,
+    /// Short description, shown in the results.
+    pub summary: String,
+    /// Topics, media type...
+    pub keywords: Vec<String>,
+    /// Full text of the page, as HTML.
+    pub body: String,
+}
+
+/// The documents registered in a build.
+///
+/// Can be shared between threads, since entries are generated in parallel.
+#[derive(Debug, Default)]
+pub struct DocumentRegistry {
+    documents: Mutex<Vec<Document>>,
+}
+
+impl DocumentRegistry {
+    pub fn register(&self, document: Document) {
+        self.documents
+            .lock()
+            .expect("Search document lock shouldn't be poisoned")
+            .push(document);
+    }
+
+    /// The registered documents, sorted by URL.
+    pub fn documents(&self) -> Vec<Document> {
+        let mut documents = self
+            .documents
+            .lock()
+            .expect("Search document lock shouldn't be poisoned")
+            .clone();
+        documents.sort_by(|a, b| a.url.cmp(&b.url));
+        documents
+    }
+}
+
+/// Prebuilt search index, as read by `search.js`.
+#[derive(Debug, Serialize)]
+pub struct SearchIndex {
+    /// What is shown of each document in the results.
+    docs: Vec<IndexedDocument>,
+    /// For each term, the documents that contain it as pairs of index in
+    /// [`Self::docs`] and weight.
+    terms: BTreeMap<String, Vec<(usize, u32)>>,
+}
+
+#[derive(Debug, Serialize)]
+struct IndexedDocument {
+    url: String,
+    title: String,
+    kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date: Option<Date>,
+    summary: String,
+}
+
+impl SearchIndex {
+    pub fn new(documents: &[Document]) -> Self {
+        let mut terms = BTreeMap::<String, Vec<(usize, u32)>>::new();
+        for (i, document) in documents.iter().enumerate() {
+            // Highest weight of the parts of the document with each term.
+            let mut weights = BTreeMap::<String, u32>::new();
+            let mut add = |text: &str, weight: u32| {
+                for term in tokenize(text) {
+                    let max = weights.entry(term).or_default();
+                    *max = (*max).max(weight);
+                }
+            };
+            add(&document.title, TITLE_WEIGHT);
+            for keyword in &document.keywords {
+                add(keyword, KEYWORD_WEIGHT);
+            }
+            add(&document.summary, SUMMARY_WEIGHT);
+
+            let mut body = BTreeMap::<String, u32>::new();
+            for term in tokenize(&text(&document.body)) {
+                let count = body.entry(term).or_default();
+                *count = (*count + 1).min(MAX_BODY_WEIGHT);
+            }
+            for (term, count) in body {
+                *weights.entry(term).or_default() += count;
+            }
+
+            for (term, weight) in weights {
+                terms.entry(term).or_default().push((i, weight));
+            }
+        }
+
+        let docs = documents
+            .iter()
+            .map(|document| IndexedDocument {
+                url: document.url.clone(),
+                title: document.title.clone(),
+                kind: document.kind.clone(),
+                date: document.date,
+                summary: shorten(&document.summary),
+            })
+            .collect();
+
+        Self { docs, terms }
+    }
+}
+
+/// Splits `text` in lowercase terms, in the same way as [`tokenize_script`]
+/// does with the query.
+///
+/// Terms are runs of alphabetic characters and numbers (as in Unicode, which
+/// includes the combining vowels of some scripts) with at least
+/// [`MIN_TERM_LENGTH`] characters.
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= MIN_TERM_LENGTH)
+        .map(str::to_lowercase)
+}
+
+/// The `tokenize` function of the search script, which follows the same rules
+/// as [`tokenize`].
+pub fn tokenize_script() -> String {
+    include_str!("./tokenize.js").replace("{{MIN_TERM_LENGTH}}", &MIN_TERM_LENGTH.to_string())
+}
+
+/// The text of `html`, without the markup of rendered math (which is just
+/// noise for the search).
+pub fn text(html: &str) -> String {
+    let fragment = Html::parse_fragment(html);
+    let mut text = String::new();
+    for node in fragment.root_element().descendants() {
+        let Node::Text(content) = node.value() else {
+            continue;
+        };
+
+        let in_math = node.ancestors().any(|ancestor| {
+            ancestor
+                .value()
+                .as_element()
+                .is_some_and(|element| element.classes().any(|class| class == "katex"))
+        });
+        if !in_math {
+            text.push_str(content);
+            text.push(' ');
+        }
+    }
+
+    text
+}
+
+/// `text` in a single line, cut to [`MAX_SUMMARY_LENGTH`] characters to keep
+/// the index small.
+fn shorten(text: &str) -> String {
+    let mut summary = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if let Some((cut, _)) = summary.char_indices().nth(MAX_SUMMARY_LENGTH) {
+        summary.truncate(cut);
+        summary.push('…');
+    }
+    summary
+}
+
+/// The search page.
+pub fn page() -> Markup {
+    let script = tokenize_script()
+        + &include_str!("./search.js").replace("{{SEARCH_INDEX_PATH}}", INDEX_PATH);
+
+    html! {
+        (back())
+
+        h1 { "search" }
+        p."pb-4 faint" { (DESC) }
+
+        input #search-input."w-full mb-6 px-2 py-1 outline-1 outline-primary/50 rounded-xs bg-neutral text-primary"
+            type="search" placeholder="type to search..." autocomplete="off" autofocus;
+
+        p #search-status."faint text-sm mb-4" {}
+        ul #search-results {}
+
+        noscript { p."faint" { "The search needs JavaScript, sorry!" } }
+
+        script {
+            (PreEscaped(script))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The search index and the queries of the search page are tokenized the
+    /// same way, so that every term can be found.
+    #[test]
+    fn tokenize_matches_script() {
+        let context = quick_js::Context::new().unwrap();
+        context.eval(&tokenize_script()).unwrap();
+
+        for text in [
+            "Hello, World! It's a test.",
+            "C++ and rust-lang in 2024, v1.2",
+            "naïve café, a b c",
+            "Ⅻ chapters, ½ cup, x² and 3rd",
+            "नमस्ते दुनिया",
+            "ΟΔΟΣ Σίσυφος",
+            "日本語のテキスト",
+            "İstanbul ǅemal",
+            "tab\tseparated\nlines—and…dashes",
+        ] {
+            let expected = tokenize(text).collect::<Vec<_>>();
+            let actual = match context.call_function("tokenize", [text]).unwrap() {
+                quick_js::JsValue::Array(words) => words
+                    .into_iter()
+                    .map(|word| match word {
+                        quick_js::JsValue::String(word) => word,
+                        other => panic!("Not a word: {other:?}"),
+                    })
+                    .collect::<Vec<_>>(),
+                other => panic!("Not a list of words: {other:?}"),
+            };
+            assert_eq!(actual, expected, "Different terms for {text:?}");
+        }
+    }
+}
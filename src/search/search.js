@@ -0,0 +1,169 @@
+const input = document.getElementById("search-input");
+const status = document.getElementById("search-status");
+const results = document.getElementById("search-results");
+
+let index = null;
+
+async function loadIndex() {
+  if (index === null) {
+    const response = await fetch("/{{SEARCH_INDEX_PATH}}");
+    index = await response.json();
+    index.termList = Object.keys(index.terms);
+  }
+  return index;
+}
+
+// `tokenize` comes from `tokenize.js`, which is shared with the tests.
+
+// Edit distance between `a` and `b`, or `max + 1` if it's more than `max`.
+function distance(a, b, max) {
+  if (Math.abs(a.length - b.length) > max) {
+    return max + 1;
+  }
+
+  let previous = Array.from({ length: b.length + 1 }, (_, i) => i);
+  for (let i = 1; i <= a.length; i++) {
+    const current = [i];
+    let rowMin = i;
+    for (let j = 1; j <= b.length; j++) {
+      const cost = a[i - 1] === b[j - 1] ? 0 : 1;
+      current[j] = Math.min(
+        previous[j] + 1,
+        current[j - 1] + 1,
+        previous[j - 1] + cost,
+      );
+      rowMin = Math.min(rowMin, current[j]);
+    }
+    if (rowMin > max) {
+      return max + 1;
+    }
+    previous = current;
+  }
+
+  return previous[b.length];
+}
+
+// How different a term can be from a word of the query to still match it.
+function typoTolerance(word) {
+  if (word.length >= 8) return 2;
+  if (word.length >= 4) return 1;
+  return 0;
+}
+
+// Scores of the documents that match `word`, by index.
+function matches(index, word) {
+  const scores = new Map();
+  const tolerance = typoTolerance(word);
+
+  for (const term of index.termList) {
+    let factor;
+    if (term === word) {
+      factor = 2;
+    } else if (term.startsWith(word)) {
+      factor = 1;
+    } else if (tolerance > 0 && distance(term, word, tolerance) <= tolerance) {
+      factor = 0.5;
+    } else {
+      continue;
+    }
+
+    for (const [doc, weight] of index.terms[term]) {
+      scores.set(doc, Math.max(scores.get(doc) ?? 0, weight * factor));
+    }
+  }
+
+  return scores;
+}
+
+// Documents that match every word of the query, best first.
+function search(index, query) {
+  const words = tokenize(query);
+  if (words.length === 0) {
+    return [];
+  }
+
+  let total = null;
+  for (const word of words) {
+    const scores = matches(index, word);
+    if (total === null) {
+      total = scores;
+      continue;
+    }
+
+    for (const [doc, score] of total) {
+      if (scores.has(doc)) {
+        total.set(doc, score + scores.get(doc));
+      } else {
+        total.delete(doc);
+      }
+    }
+  }
+
+  return [...total]
+    .sort((a, b) => b[1] - a[1])
+    .map(([doc]) => index.docs[doc]);
+}
+
+function render(docs) {
+  results.replaceChildren(
+    ...docs.map((doc) => {
+      const item = document.createElement("li");
+      item.className = "mb-4";
+
+      const link = document.createElement("a");
+      link.href = doc.url;
+
+      const title = document.createElement("div");
+      title.className = "font-bold text-lg";
+      title.textContent = `> ${doc.title}`;
+
+      const info = document.createElement("div");
+      info.className = "text-sm text-tertiary faint";
+      info.textContent = doc.date ? `${doc.kind} · ${doc.date}` : doc.kind;
+
+      const summary = document.createElement("div");
+      summary.className = "text-sm opacity-50 line-clamp-2";
+      summary.textContent = doc.summary;
+
+      link.append(title, info, summary);
+      item.append(link);
+      return item;
+    }),
+  );
+}
+
+async function update() {
+  const query = input.value;
+
+  const url = new URL(window.location);
+  if (query) {
+    url.searchParams.set("q", query);
+  } else {
+    url.searchParams.delete("q");
+  }
+  window.history.replaceState(null, "", url);
+
+  if (tokenize(query).length === 0) {
+    status.textContent = "";
+    results.replaceChildren();
+    return;
+  }
+
+  const found = search(await loadIndex(), query);
+  // The query might have changed while loading the index.
+  if (input.value !== query) {
+    return;
+  }
+
+  status.textContent =
+    found.length === 1 ? "1 result" : `${found.length} results`;
+  render(found);
+}
+
+input.addEventListener("input", update);
+
+const initial = new URLSearchParams(window.location.search).get("q");
+if (initial) {
+  input.value = initial;
+  update();
+}
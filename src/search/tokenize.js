@@ -0,0 +1,9 @@
+// Same rules as `search::tokenize` in the generator: words are runs of
+// alphabetic characters and numbers, of at least {{MIN_TERM_LENGTH}} characters,
+// in lowercase.
+function tokenize(text) {
+  return text
+    .split(/[^\p{Alphabetic}\p{N}]+/u)
+    .filter((word) => [...word].length >= {{MIN_TERM_LENGTH}})
+    .map((word) => word.toLowerCase());
+}
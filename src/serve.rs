@@ -0,0 +1,171 @@
+//! Local development server, with live reload.
+//!
+//! Serves the output directory over HTTP and rebuilds the site whenever
+//! something in the watched directories changes. Pages generated with
+//! [`BuildOptions::live_reload`] include a small script (see [`components::live_reload_script`]) that polls
+//! [`LIVE_RELOAD_PATH`] and refreshes the page after each rebuild.
+//!
+//! [`components::live_reload_script`]: crate::components::live_reload_script
+//! [`BuildOptions::live_reload`]: crate::config::BuildOptions::live_reload
+
+use color_eyre::eyre::{self, WrapErr as _};
+use notify::{RecursiveMode, Watcher as _};
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Duration,
+};
+use tiny_http::{Header, Request, Response, Server};
+
+/// Path that the live reload script polls for the current build version.
+pub const LIVE_RELOAD_PATH: &str = "/__livereload";
+
+/// How long to wait for more file events before rebuilding.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Serves `output` at `address`, calling `rebuild` every time a file in
+/// `watch` changes.
+///
+/// Doesn't return unless there is an error.
+pub fn serve(
+    output: &Path,
+    address: &str,
+    watch: &[PathBuf],
+    mut rebuild: impl FnMut() -> eyre::Result<()>,
+) -> eyre::Result<()> {
+    let server = Server::http(address)
+        .map_err(|err| eyre::eyre!("Couldn't start server at {address}: {err}"))?;
+    tracing::info!("Serving site at http://{address}");
+
+    // Incremented after every rebuild.
+    let version = Arc::new(AtomicU64::new(0));
+
+    {
+        let output = output.to_path_buf();
+        let version = Arc::clone(&version);
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                if let Err(err) = respond(request, &output, &version) {
+                    tracing::warn!(?err, "Couldn't respond to request");
+                }
+            }
+        });
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(sender).wrap_err("Couldn't create file watcher")?;
+    for path in watch {
+        watcher
+            .watch(path, RecursiveMode::Recursive)
+            .wrap_err_with(|| format!("Couldn't watch {path:?}"))?;
+        tracing::info!(?path, "Watching for changes");
+    }
+
+    while let Ok(event) = receiver.recv() {
+        match event {
+            // Reading the sources during the build shouldn't trigger another one.
+            Ok(event) if event.kind.is_access() => continue,
+            Ok(_) => (),
+            Err(err) => {
+                tracing::warn!(?err, "File watcher error");
+                continue;
+            }
+        }
+
+        // Wait until changes settle, editors usually write several times.
+        while receiver.recv_timeout(DEBOUNCE).is_ok() {}
+
+        tracing::info!("Change detected, rebuilding");
+        match rebuild() {
+            Ok(()) => tracing::info!("Rebuilt site"),
+            Err(err) => tracing::error!(?err, "Rebuild failed"),
+        }
+
+        // Reload even if the build failed, since some pages might have changed.
+        version.fetch_add(1, Ordering::Relaxed);
+    }
+
+    eyre::bail!("File watcher stopped unexpectedly")
+}
+
+fn respond(request: Request, output: &Path, version: &AtomicU64) -> eyre::Result<()> {
+    let url = request.url().to_string();
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+
+    if path == LIVE_RELOAD_PATH {
+        let version = version.load(Ordering::Relaxed).to_string();
+        return Ok(request
+            .respond(Response::from_string(version).with_header(content_type("text/plain")))?);
+    }
+
+    let Some(file) = resolve(output, path) else {
+        tracing::debug!(?path, "Not found");
+        let not_found = output.join("404.html");
+        let response = match fs::read(&not_found) {
+            Ok(page) => Response::from_data(page).with_header(content_type(mime_type(&not_found))),
+            Err(_) => Response::from_string("404 Not Found"),
+        };
+        return Ok(request.respond(response.with_status_code(404))?);
+    };
+
+    let data = fs::read(&file).wrap_err_with(|| format!("Couldn't read {file:?}"))?;
+    let mime = mime_type(&file);
+    Ok(request.respond(Response::from_data(data).with_header(content_type(mime)))?)
+}
+
+/// Finds the file in `output` for the URL `path`, like a static host would:
+/// directories (and extensionless paths) resolve to their `index.html`.
+pub(crate) fn resolve(output: &Path, path: &str) -> Option<PathBuf> {
+    let decoded = percent_encoding::percent_decode_str(path)
+        .decode_utf8()
+        .ok()?;
+    let relative = Path::new(decoded.trim_start_matches('/'));
+
+    // Don't let requests escape the output directory.
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+
+    let file = output.join(relative);
+    if file.is_file() {
+        return Some(file);
+    }
+
+    let index = file.join("index.html");
+    index.is_file().then_some(index)
+}
+
+fn mime_type(file: &Path) -> &'static str {
+    match file.extension().and_then(|ext| ext.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("xml") => "application/xml",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("woff2") => "font/woff2",
+        Some("woff") => "font/woff",
+        Some("ttf") => "font/ttf",
+        Some("pdf") => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+fn content_type(mime: &str) -> Header {
+    Header::from_bytes("Content-Type", mime).expect("Content types are valid headers")
+}
@@ -0,0 +1,124 @@
+//! Generation of `sitemap.xml` and `robots.txt`.
+//!
+//! Pages are registered in a [`PageRegistry`] as they are generated, together
+//! with the date they were last modified (if it's known), and the sitemap is
+//! made from the registry at the end of the build. Since every page goes
+//! through it, the registry also catches pages that would overwrite each
+//! other (like a post with the slug `archive` and the archive of the blog).
+//!
+//! Like the feeds, the sitemap is written with the [`html!`] macro.
+
+use color_eyre::eyre;
+use jiff::civil::Date;
+use maud::{Markup, PreEscaped, html};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::config::SiteConfig;
+
+/// The pages generated in a build.
+///
+/// Can be shared between threads, since pages are generated in parallel.
+#[derive(Debug, Default)]
+pub struct PageRegistry {
+    /// Every page, by path relative to the output directory.
+    pages: Mutex<BTreeMap<PathBuf, Page>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Page {
+    lastmod: Option<Date>,
+    /// Whether the page is in the sitemap.
+    listed: bool,
+}
+
+impl PageRegistry {
+    /// Registers the page at `path` (relative to the output directory), which
+    /// is only in the sitemap if it's `listed`.
+    ///
+    /// Fails if another page was already registered at `path`, since one of
+    /// them would overwrite the other.
+    pub fn register(&self, path: &Path, lastmod: Option<Date>, listed: bool) -> eyre::Result<()> {
+        let mut pages = self
+            .pages
+            .lock()
+            .expect("Page registry lock shouldn't be poisoned");
+        if pages.contains_key(path) {
+            eyre::bail!(
+                "Two pages would be written to {path:?}, rename the entry (or the topic or \
+                 series) with that path"
+            );
+        }
+
+        pages.insert(path.to_path_buf(), Page { lastmod, listed });
+        Ok(())
+    }
+
+    /// The pages in the sitemap, sorted by path.
+    pub fn pages(&self) -> BTreeMap<PathBuf, Option<Date>> {
+        self.pages
+            .lock()
+            .expect("Page registry lock shouldn't be poisoned")
+            .iter()
+            .filter(|(_, page)| page.listed)
+            .map(|(path, page)| (path.clone(), page.lastmod))
+            .collect()
+    }
+}
+
+/// The sitemap with the given `pages`, as returned by [`PageRegistry::pages`].
+pub fn sitemap(config: &SiteConfig, pages: &BTreeMap<PathBuf, Option<Date>>) -> Markup {
+    html! {
+        (PreEscaped(r#"<?xml version="1.0" encoding="UTF-8"?>"#))
+        urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9" {
+            @for (path, lastmod) in pages {
+                url {
+                    loc { (config.page_url(path)) }
+                    @if let Some(date) = lastmod {
+                        lastmod { (date) }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// `robots.txt` allowing everything and pointing to the sitemap.
+pub fn robots(config: &SiteConfig) -> String {
+    format!(
+        "User-agent: *\nAllow: /\n\nSitemap: {}\n",
+        config.url("sitemap.xml")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_have_different_paths() {
+        let registry = PageRegistry::default();
+        let path = Path::new("blog/archive/index.html");
+        registry.register(path, None, true).unwrap();
+        registry
+            .register(Path::new("blog/secret/index.html"), None, false)
+            .unwrap();
+
+        let err = registry.register(path, None, true).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Two pages would be written to \"blog/archive/index.html\""),
+            "Unexpected error: {err}"
+        );
+        assert!(
+            registry
+                .register(Path::new("blog/secret/index.html"), None, true)
+                .is_err(),
+            "Unlisted pages can be overwritten too"
+        );
+        assert_eq!(registry.pages().into_keys().collect::<Vec<_>>(), [path]);
+    }
+}
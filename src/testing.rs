@@ -0,0 +1,43 @@
+//! Helpers for the unit tests, which use the fixture site of the snapshot
+//! tests (in `tests/fixtures`).
+
+use std::path::Path;
+
+use crate::{
+    blog::BlogEntry,
+    config::{BuildOptions, SiteConfig},
+};
+
+/// Configuration of the fixture site, for an offline release build so that
+/// nothing is fetched and problems of the content are errors, on a fixed date.
+pub fn config() -> SiteConfig {
+    let options = BuildOptions {
+        release: true,
+        offline: true,
+        today: jiff::civil::date(2025, 1, 1),
+        ..Default::default()
+    };
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/site.toml");
+    SiteConfig::load(path, options).expect("Fixture config is valid")
+}
+
+/// Like [`config`], but for a development build, where problems of the content
+/// are shown in the pages and warned about instead.
+pub fn dev_config() -> SiteConfig {
+    let mut config = config();
+    config.build.release = false;
+    config
+}
+
+/// A published blog post with `title`, the other front matter `fields` (each
+/// in its own line) and `body`.
+pub fn post(title: &str, fields: &str, body: &str) -> String {
+    format!("---\ntitle: {title}\ndraft: false\n{fields}---\n\n{body}")
+}
+
+/// Parses the published blog post `slug`, failing the test if it's invalid.
+pub fn entry(slug: &str, content: &str, config: &SiteConfig) -> BlogEntry {
+    BlogEntry::from_slug_and_content(slug, content, config)
+        .unwrap_or_else(|err| panic!("Couldn't parse {slug:?}: {err:?}"))
+        .expect("The post is published")
+}
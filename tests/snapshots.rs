@@ -0,0 +1,243 @@
+//! Golden-file tests of the renderers, with the content in `tests/fixtures`.
+//!
+//! The expected outputs are in `tests/golden`. To accept the current outputs
+//! after an intended change, run the tests with `UPDATE_GOLDEN=1` and review
+//! the diff.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use odilf_site::{
+    about,
+    blog::{self, BlogEntry},
+    collection::{self, Collection, DerivedOutput},
+    config::{BuildOptions, SiteConfig},
+    home, media,
+    media::MediaLog,
+    pics::{self, immich::Photo},
+    projects::{self, Projects},
+    search::SearchIndex,
+};
+
+fn fixtures() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+/// Configuration of the fixture site, for an offline release build so that
+/// nothing is fetched and drafts are left out, built on a fixed date.
+fn config() -> SiteConfig {
+    let options = BuildOptions {
+        release: true,
+        offline: true,
+        today: jiff::civil::date(2025, 1, 1),
+        ..Default::default()
+    };
+    SiteConfig::load(fixtures().join("site.toml"), options).expect("Fixture config is valid")
+}
+
+/// Parses all the entries of the collection `T`, sorted like in the site.
+fn entries<T: Collection>(config: &SiteConfig) -> Vec<T> {
+    let mut paths = fs::read_dir(T::source_dir(config))
+        .expect("Fixture directory exists")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let mut entries = paths
+        .iter()
+        .filter_map(|path| {
+            let slug = path.file_stem().unwrap().to_str().unwrap();
+            let content = fs::read_to_string(path).unwrap();
+            T::parse(slug, &content, config)
+                .unwrap_or_else(|err| panic!("Couldn't parse {path:?}: {err:?}"))
+        })
+        .collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+    entries
+}
+
+/// The entries that are shown in the index and the feeds.
+fn listed<T: Collection>(entries: &[T]) -> impl Iterator<Item = &T> + Clone {
+    entries.iter().filter(|entry| entry.is_listed())
+}
+
+fn photos() -> Vec<Photo> {
+    let album = fs::read_to_string(fixtures().join("immich/album.json")).unwrap();
+    serde_json::from_str(&album).expect("Fixture album is valid")
+}
+
+/// Compares `actual` with the golden file `name`, or overwrites the golden
+/// file if `UPDATE_GOLDEN` is set.
+#[track_caller]
+fn assert_golden(name: &str, actual: impl AsRef<str>) {
+    // Everything is rendered in a single line, split it so that diffs are readable.
+    let actual = actual.as_ref().replace("><", ">\n<") + "\n";
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name);
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!("Missing golden file {path:?}, run with `UPDATE_GOLDEN=1` to create it")
+    });
+
+    if expected != actual {
+        let diff = similar::TextDiff::from_lines(&expected, &actual);
+        panic!(
+            "Output doesn't match {path:?} (run with `UPDATE_GOLDEN=1` to accept it):\n{}",
+            diff.unified_diff().header("expected", "actual")
+        );
+    }
+}
+
+/// Replaces the dates of a feed, which are either the time of the build or in
+/// the local time zone, so that the golden file doesn't depend on them.
+fn mask_dates(feed: &str) -> String {
+    ["pubDate", "lastBuildDate", "updated"]
+        .iter()
+        .fold(feed.to_string(), |feed, tag| {
+            let (open, close) = (format!("<{tag}>"), format!("</{tag}>"));
+            let mut masked = String::new();
+            let mut rest = feed.as_str();
+            while let Some(start) = rest.find(&open) {
+                let start = start + open.len();
+                let end = start + rest[start..].find(&close).expect("Tags are closed");
+                masked.push_str(&rest[..start]);
+                masked.push_str("DATE");
+                rest = &rest[end..];
+            }
+            masked.push_str(rest);
+            masked
+        })
+}
+
+#[test]
+fn home_page() {
+    assert_golden("home.html", home().0);
+}
+
+#[test]
+fn about_page() {
+    assert_golden("about.html", about().0);
+}
+
+#[test]
+fn blog_home() {
+    let config = config();
+    let entries = entries::<BlogEntry>(&config);
+    let page = blog::Page::first(&config);
+    assert_golden("blog/index.html", blog::home(listed(&entries), page).0);
+}
+
+#[test]
+fn blog_entries() {
+    let entries = entries::<BlogEntry>(&config());
+    let slugs = entries
+        .iter()
+        .map(|entry| entry.slug.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        slugs,
+        ["calculus", "secret", "hello", "old-setup"],
+        "Drafts and future scheduled posts should be skipped"
+    );
+
+    let neighbours = collection::neighbours(&entries);
+    for (entry, neighbours) in entries.iter().zip(&neighbours) {
+        assert_golden(
+            &format!("blog/{}.html", entry.slug),
+            entry.render_page(neighbours).0,
+        );
+    }
+}
+
+#[test]
+fn blog_feeds() {
+    let config = config();
+    let entries = entries::<BlogEntry>(&config);
+    let rss = blog::feed::rss(&config, None, listed(&entries)).unwrap();
+    let atom = blog::feed::atom(&config, None, listed(&entries)).unwrap();
+
+    assert_golden("blog/rss.xml", mask_dates(&rss.0));
+    assert_golden("blog/atom.xml", mask_dates(&atom.0));
+}
+
+#[test]
+fn blog_derived_outputs() {
+    let config = config();
+    let entries = entries::<BlogEntry>(&config)
+        .into_iter()
+        .filter(Collection::is_listed)
+        .collect::<Vec<_>>();
+
+    for output in BlogEntry::derived_outputs(&config, &entries).unwrap() {
+        match output {
+            DerivedOutput::Page { path, content, .. } => assert_golden(&path, content.0),
+            DerivedOutput::File { path, content } => assert_golden(&path, mask_dates(&content)),
+        }
+    }
+}
+
+#[test]
+fn media_home() {
+    let entries = entries::<MediaLog>(&config());
+    assert_golden("media-log/index.html", media::home(entries.iter()).0);
+}
+
+#[test]
+fn media_extra_outputs() {
+    let config = config();
+    let entries = entries::<MediaLog>(&config);
+    for output in MediaLog::EXTRA_OUTPUTS {
+        assert_golden(output.path, (output.render)(&config, &entries).unwrap());
+    }
+}
+
+#[test]
+fn search_index() {
+    let config = config();
+    let blog = entries::<BlogEntry>(&config);
+    let media = entries::<MediaLog>(&config);
+    let mut documents = listed(&blog)
+        .filter_map(Collection::search_document)
+        .chain(listed(&media).filter_map(Collection::search_document))
+        .collect::<Vec<_>>();
+    documents.sort_by(|a, b| a.url.cmp(&b.url));
+
+    let index = SearchIndex::new(&documents);
+    assert_golden(
+        "search-index.json",
+        serde_json::to_string_pretty(&index).unwrap(),
+    );
+}
+
+#[test]
+fn pics_pages() {
+    let photos = photos();
+    let ids = photos
+        .iter()
+        .map(|photo| photo.id.clone())
+        .collect::<Vec<_>>();
+
+    for (index, photo) in photos.iter().enumerate() {
+        assert_golden(
+            &format!("pics/{index}.html"),
+            pics::pic(photo, index, &ids).0,
+        );
+    }
+}
+
+#[test]
+fn projects_home() {
+    let content = fs::read_to_string(fixtures().join("projects.toml")).unwrap();
+    let projects: Projects = toml::from_str(&content).expect("Fixture projects are valid");
+    assert_golden("projects.html", projects::home(&projects).0);
+}